@@ -1,16 +1,46 @@
 #version 330 core
 out vec4 FragColor;
 
-in vec2 TexCoord;
+in vec2 CellCoord;
+flat in vec4 TextureRect;
+flat in vec4 GlyphQuad;
+flat in int Style;
 in vec4 BackgroundColor;
 in vec4 ForegroundColor;
 
 uniform sampler2D texture1;
 
+const int BOLD = 1;
+const int ITALIC = 2;
+const int UNDERLINE = 4;
+const int STRIKETHROUGH = 8;
+
+// Outside the glyph's own quad the cell only shows its background
+vec4 sampleGlyph(vec2 cellCoord)
+{
+    vec2 glyphCoord = (cellCoord - GlyphQuad.xy) / (GlyphQuad.zw - GlyphQuad.xy);
+    if (any(lessThan(glyphCoord, vec2(0.0))) || any(greaterThanEqual(glyphCoord, vec2(1.0)))) {
+        return vec4(0.0);
+    }
+    return texture(texture1, mix(TextureRect.xy, TextureRect.zw, glyphCoord));
+}
 
 void main()
 {
-    vec4 texColor = texture(texture1, TexCoord);
+    vec2 cellCoord = CellCoord;
+    if ((Style & ITALIC) != 0) {
+        // Leans the glyph right, shifting rows more the higher they are
+        cellCoord.x -= (cellCoord.y - 0.25) * 0.2;
+    }
+    vec4 texColor = sampleGlyph(cellCoord);
+    if ((Style & BOLD) != 0) {
+        // A copy a little to the left thickens every vertical stroke
+        texColor = max(texColor, sampleGlyph(cellCoord + vec2(0.06, 0.0)));
+    }
+    if (((Style & UNDERLINE) != 0 && CellCoord.y >= 0.06 && CellCoord.y < 0.12)
+        || ((Style & STRIKETHROUGH) != 0 && CellCoord.y >= 0.42 && CellCoord.y < 0.48)) {
+        texColor = vec4(1.0);
+    }
     if (texColor.a < 0.1) {
         if (BackgroundColor.a < 0.1) {
             discard;
@@ -1,17 +1,33 @@
 #version 330 core
-layout (location = 0) in vec3 aPos;
-layout (location = 1) in vec2 aTexCoord;
-layout (location = 2) in vec4 backgroundColor;
-layout (location = 3) in vec4 foregroundColor;
+layout (location = 0) in vec2 corner;
+layout (location = 1) in vec3 cell;
+layout (location = 2) in vec4 textureRect;
+layout (location = 3) in vec4 backgroundColor;
+layout (location = 4) in vec4 foregroundColor;
+layout (location = 5) in vec4 glyphQuad;
 
-out vec2 TexCoord;
+uniform vec2 dimensions;
+uniform vec2 screenScale;
+uniform vec2 screenOffset;
+uniform float depth;
+
+out vec2 CellCoord;
+flat out vec4 TextureRect;
+flat out vec4 GlyphQuad;
+flat out int Style;
 out vec4 BackgroundColor;
 out vec4 ForegroundColor;
 
 void main()
 {
-    gl_Position = vec4(aPos, 1.0);
-    TexCoord = aTexCoord;
+    float row = floor((cell.x + 0.5) / dimensions.x);
+    vec2 coordinates = vec2(cell.x - row * dimensions.x, row);
+    vec2 position = ((coordinates + corner) / dimensions * 2.0 - 1.0) * screenScale + screenOffset;
+    gl_Position = vec4(position, cell.y / 255.0 * -1.0 * depth, 1.0);
+    CellCoord = corner;
+    TextureRect = textureRect;
+    GlyphQuad = glyphQuad;
+    Style = int(cell.z + 0.5);
     BackgroundColor = backgroundColor;
     ForegroundColor = foregroundColor;
-}
\ No newline at end of file
+}
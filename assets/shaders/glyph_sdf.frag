@@ -0,0 +1,55 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 CellCoord;
+flat in vec4 TextureRect;
+flat in vec4 GlyphQuad;
+flat in int Style;
+in vec4 BackgroundColor;
+in vec4 ForegroundColor;
+
+uniform sampler2D texture1;
+
+const int BOLD = 1;
+const int ITALIC = 2;
+const int UNDERLINE = 4;
+const int STRIKETHROUGH = 8;
+
+// Zero outside the glyph's own quad
+float distanceAt(vec2 cellCoord)
+{
+    vec2 glyphCoord = (cellCoord - GlyphQuad.xy) / (GlyphQuad.zw - GlyphQuad.xy);
+    float inQuad = step(0.0, glyphCoord.x) * step(0.0, glyphCoord.y)
+        * step(glyphCoord.x, 1.0) * step(glyphCoord.y, 1.0);
+    vec2 texCoord = mix(TextureRect.xy, TextureRect.zw, clamp(glyphCoord, 0.0, 1.0));
+    return texture(texture1, texCoord).a * inQuad;
+}
+
+void main()
+{
+    vec2 cellCoord = CellCoord;
+    if ((Style & ITALIC) != 0) {
+        // Leans the glyph right, shifting rows more the higher they are
+        cellCoord.x -= (cellCoord.y - 0.25) * 0.2;
+    }
+    // Sampled outside any branch so the derivatives below stay defined
+    float distance = distanceAt(cellCoord);
+    // Bold moves the outline outwards
+    float edge = (Style & BOLD) != 0 ? 0.42 : 0.5;
+    float smoothing = max(fwidth(distance), 0.001);
+    float coverage = smoothstep(edge - smoothing, edge + smoothing, distance);
+    if (((Style & UNDERLINE) != 0 && CellCoord.y >= 0.06 && CellCoord.y < 0.12)
+        || ((Style & STRIKETHROUGH) != 0 && CellCoord.y >= 0.42 && CellCoord.y < 0.48)) {
+        coverage = 1.0;
+    }
+
+    vec4 foreground = ForegroundColor.a < 0.1 ? vec4(1.0) : ForegroundColor;
+    if (BackgroundColor.a < 0.1) {
+        if (coverage < 0.5) {
+            discard;
+        }
+        FragColor = foreground;
+    } else {
+        FragColor = mix(BackgroundColor, foreground, coverage);
+    }
+}
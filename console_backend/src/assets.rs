@@ -0,0 +1,82 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use image::DynamicImage;
+use crate::font_registry::FontRegistry;
+use crate::render_gl::Program;
+use crate::render_gl::texture::Texture;
+use crate::resources::Resources;
+
+struct AssetCache {
+    gl: gl::Gl,
+    res: Resources,
+    programs: RefCell<HashMap<String, Rc<Program>>>,
+    textures: RefCell<HashMap<String, Rc<Texture>>>,
+    fonts: FontRegistry,
+}
+
+// Loads every program, texture and font once and hands out shared handles to it.
+// Clones refer to the same cache.
+#[derive(Clone)]
+pub struct Assets {
+    cache: Rc<AssetCache>,
+}
+
+impl Assets {
+    pub fn new(gl: &gl::Gl, res: Resources) -> Self {
+        Assets {
+            cache: Rc::new(AssetCache {
+                gl: gl.clone(),
+                res,
+                programs: RefCell::new(HashMap::new()),
+                textures: RefCell::new(HashMap::new()),
+                fonts: FontRegistry::new(),
+            }),
+        }
+    }
+
+    pub fn gl(&self) -> &gl::Gl {
+        &self.cache.gl
+    }
+
+    pub fn resources(&self) -> &Resources {
+        &self.cache.res
+    }
+
+    pub fn fonts(&self) -> &FontRegistry {
+        &self.cache.fonts
+    }
+
+    // name is the shader path without extension, like Program::from_res
+    pub fn program(&self, name: &str) -> Result<Rc<Program>, failure::Error> {
+        let files = [format!("{}.vert", name), format!("{}.frag", name)];
+        self.program_from_files(name, &[&files[0], &files[1]])
+    }
+
+    // Cached under name, which doesn't have to match any of the files
+    pub fn program_from_files(&self, name: &str, files: &[&str]) -> Result<Rc<Program>, failure::Error> {
+        if let Some(program) = self.cache.programs.borrow().get(name) {
+            return Ok(program.clone());
+        }
+        let program = Rc::new(Program::from_res_files(&self.cache.gl, &self.cache.res, name, files)?);
+        self.cache.programs.borrow_mut().insert(name.to_string(), program.clone());
+        Ok(program)
+    }
+
+    pub fn texture(&self, name: &str) -> Result<Rc<Texture>, failure::Error> {
+        if let Some(texture) = self.cache.textures.borrow().get(name) {
+            return Ok(texture.clone());
+        }
+        let img = self.cache.res.load_image(name)?;
+        let texture = Rc::new(Texture::from_img(&self.cache.gl, DynamicImage::ImageRgba8(img.to_rgba()), gl::RGBA)?);
+        self.cache.textures.borrow_mut().insert(name.to_string(), texture.clone());
+        Ok(texture)
+    }
+
+    // Frees whatever nothing outside the cache holds on to anymore
+    pub fn purge_unused(&self) {
+        self.cache.programs.borrow_mut().retain(|_, program| Rc::strong_count(program) > 1);
+        self.cache.textures.borrow_mut().retain(|_, texture| Rc::strong_count(texture) > 1);
+        self.cache.fonts.purge_unused();
+    }
+}
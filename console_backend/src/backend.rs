@@ -0,0 +1,118 @@
+use crate::cell_buffer::CellBuffer;
+use crate::font_info::FontInfo;
+
+// Where a console sits on screen, in normalized device coordinates
+#[derive(Copy, Clone, Debug)]
+pub struct Placement {
+    pub screen_scaling: (f32, f32),
+    pub screen_offset: (f32, f32),
+    pub scale_modifier: (f32, f32),
+    pub height: u32,
+}
+
+impl Placement {
+    pub fn glyph_size(&self, dimensions: (u32, u32)) -> (f32, f32) {
+        (2.0 / dimensions.0 as f32 * self.screen_scaling.0 * self.scale_modifier.0,
+         2.0 / dimensions.1 as f32 * self.screen_scaling.1 * self.scale_modifier.1)
+    }
+
+    pub fn coordinates_to_fractional(&self, dimensions: (u32, u32), coordinates: (u32, u32)) -> (f32, f32) {
+        (((coordinates.0 as f32 / dimensions.0 as f32) * 2.0 - 1.0) * self.screen_scaling.0 * self.scale_modifier.0 + self.screen_offset.0,
+         ((coordinates.1 as f32 / dimensions.1 as f32) * 2.0 - 1.0) * self.screen_scaling.1 * self.scale_modifier.1 + self.screen_offset.1)
+    }
+
+    // The cell under a point in normalized device coordinates, None outside the console.
+    // The right and top edges belong to the last column and row, so the window's edge pixels
+    // can be picked too.
+    pub fn fractional_to_coordinates(&self, dimensions: (u32, u32), fractional: (f32, f32)) -> Option<(u32, u32)> {
+        let cell = |fractional: f32, offset: f32, scale: f32, cells: u32| {
+            let cell = ((fractional - offset) / scale + 1.0) / 2.0 * cells as f32;
+            if cells > 0 && cell >= 0.0 && cell <= cells as f32 {
+                Some((cell as u32).min(cells - 1))
+            } else {
+                None
+            }
+        };
+        let x = cell(fractional.0, self.screen_offset.0, self.screen_scaling.0 * self.scale_modifier.0, dimensions.0)?;
+        let y = cell(fractional.1, self.screen_offset.1, self.screen_scaling.1 * self.scale_modifier.1, dimensions.1)?;
+        Some((x, y))
+    }
+}
+
+pub trait RenderBackend {
+    fn render(&mut self, cells: &CellBuffer, placement: &Placement);
+
+    fn font_info(&self) -> Option<&FontInfo> {
+        None
+    }
+
+    fn font_info_mut(&mut self) -> Option<&mut FontInfo> {
+        None
+    }
+
+    // Pixels a cell takes up at the font's own size
+    fn cell_size(&self) -> Option<(f32, f32)> {
+        self.font_info().map(FontInfo::cell_size)
+    }
+}
+
+// Draws nothing, lets consoles and widgets live without a window
+pub struct HeadlessBackend;
+
+impl RenderBackend for HeadlessBackend {
+    fn render(&mut self, _cells: &CellBuffer, _placement: &Placement) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement(screen_scaling: (f32, f32), screen_offset: (f32, f32)) -> Placement {
+        Placement {
+            screen_scaling,
+            screen_offset,
+            scale_modifier: (1.0, 1.0),
+            height: 0,
+        }
+    }
+
+    #[test]
+    fn corners_map_to_the_corner_cells() {
+        let screen = placement((1.0, 1.0), (0.0, 0.0));
+        assert_eq!(screen.fractional_to_coordinates((10, 5), (-1.0, -1.0)), Some((0, 0)));
+        assert_eq!(screen.fractional_to_coordinates((10, 5), (0.0, 0.0)), Some((5, 2)));
+        assert_eq!(screen.fractional_to_coordinates((10, 5), (-0.81, 0.99)), Some((0, 4)));
+    }
+
+    #[test]
+    fn the_top_and_right_edges_belong_to_the_last_cells() {
+        let screen = placement((1.0, 1.0), (0.0, 0.0));
+        assert_eq!(screen.fractional_to_coordinates((10, 5), (1.0, 1.0)), Some((9, 4)));
+        assert_eq!(screen.fractional_to_coordinates((10, 5), (1.0, -1.0)), Some((9, 0)));
+    }
+
+    #[test]
+    fn points_outside_the_console_have_no_cell() {
+        // The top right quarter of the screen
+        let quarter = placement((0.5, 0.5), (0.5, 0.5));
+        assert_eq!(quarter.fractional_to_coordinates((4, 4), (0.0, 0.0)), Some((0, 0)));
+        assert_eq!(quarter.fractional_to_coordinates((4, 4), (-0.01, 0.5)), None);
+        assert_eq!(quarter.fractional_to_coordinates((4, 4), (0.5, -0.01)), None);
+        assert_eq!(quarter.fractional_to_coordinates((4, 4), (1.01, 0.5)), None);
+        assert_eq!(quarter.fractional_to_coordinates((0, 4), (0.5, 0.5)), None);
+    }
+
+    #[test]
+    fn cells_map_back_to_themselves() {
+        let placement = placement((0.6, 0.3), (-0.2, 0.4));
+        let dimensions = (7, 3);
+        let glyph_size = placement.glyph_size(dimensions);
+        for y in 0..dimensions.1 {
+            for x in 0..dimensions.0 {
+                let corner = placement.coordinates_to_fractional(dimensions, (x, y));
+                let middle = (corner.0 + glyph_size.0 / 2.0, corner.1 + glyph_size.1 / 2.0);
+                assert_eq!(placement.fractional_to_coordinates(dimensions, middle), Some((x, y)));
+            }
+        }
+    }
+}
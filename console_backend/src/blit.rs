@@ -0,0 +1,129 @@
+use crate::cell_buffer::CellBuffer;
+use crate::glyph::Glyph;
+use crate::rect::Rect;
+
+// Copies src_rect of src onto dst with its bottom left corner at dst_pos, layer by layer.
+// Empty source cells leave dst alone. Below 1.0, fg_alpha and bg_alpha blend the copied
+// foreground and background with what dst already has, the way libtcod's blit does. Cells
+// are drawn opaque, so empty dst cells are blended with the background showing through them.
+pub fn blit(src: &CellBuffer, src_rect: Rect, dst: &mut CellBuffer, dst_pos: (i32, i32), fg_alpha: f32, bg_alpha: f32) {
+    let src_rect = src_rect.intersection(&Rect::with_dimensions(src.dimensions()));
+    let fg_alpha = fg_alpha.max(0.0).min(1.0);
+    let bg_alpha = bg_alpha.max(0.0).min(1.0);
+    let layers = src.layer_count().min(dst.layer_count());
+    for layer in 0..layers {
+        for y in src_rect.y..src_rect.y + src_rect.height {
+            for x in src_rect.x..src_rect.x + src_rect.width {
+                let source = match src.get_cell(x, y, layer) {
+                    Some(glyph) => *glyph,
+                    None => continue,
+                };
+                let (dst_x, dst_y) = (dst_pos.0 + (x - src_rect.x) as i32, dst_pos.1 + (y - src_rect.y) as i32);
+                if !dst.contains(dst_x, dst_y) {
+                    continue;
+                }
+                let destination = dst.get_cell(dst_x as u32, dst_y as u32, layer).cloned()
+                    .unwrap_or_else(|| {
+                        let background = dst.background_below(dst_x as u32, dst_y as u32, layer);
+                        Glyph::new(' ', background, background)
+                    });
+                let glyph = blend(source, destination, fg_alpha, bg_alpha);
+                dst.put_char_styled(glyph.character, dst_x, dst_y, glyph.foreground, Some(glyph.background), glyph.style, layer);
+            }
+        }
+    }
+}
+
+fn blend(source: Glyph, destination: Glyph, fg_alpha: f32, bg_alpha: f32) -> Glyph {
+    if fg_alpha >= 1.0 && bg_alpha >= 1.0 {
+        return source;
+    }
+    let background = destination.background.lerp(source.background, bg_alpha);
+    let mut glyph = destination;
+    glyph.background = background;
+    if source.character == ' ' {
+        // Nothing drawn in the source cell, the destination character shows through its background
+        glyph.foreground = destination.foreground.lerp(source.background, bg_alpha);
+    } else if destination.character == ' ' {
+        glyph = Glyph::styled(source.character, background, destination.background.lerp(source.foreground, fg_alpha), source.style);
+    } else if destination.character == source.character {
+        glyph.foreground = destination.foreground.lerp(source.foreground, fg_alpha);
+    } else if fg_alpha < 0.5 {
+        // The destination character fades out before the source one fades in
+        glyph.foreground = destination.foreground.lerp(destination.background, fg_alpha * 2.0);
+    } else {
+        glyph = Glyph::styled(source.character, background, destination.background.lerp(source.foreground, (fg_alpha - 0.5) * 2.0), source.style);
+    }
+    glyph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+    use crate::color::colors;
+
+    // "abc" over "def"
+    fn source() -> CellBuffer {
+        let mut src = CellBuffer::with_layers((3, 2), 1, *colors::BLACK);
+        src.put_text("abc", 0, 1, *colors::WHITE, Some(*colors::WHITE), 0);
+        src.put_text("def", 0, 0, *colors::WHITE, Some(*colors::WHITE), 0);
+        src
+    }
+
+    fn grey(level: f32) -> Color {
+        Color::new(level, level, level, 1.0)
+    }
+
+    #[test]
+    fn clips_at_the_destination_edges() {
+        let mut dst = CellBuffer::with_layers((4, 3), 1, *colors::BLACK);
+        blit(&source(), Rect::with_dimensions((3, 2)), &mut dst, (2, 1), 1.0, 1.0);
+        assert_eq!(dst.snapshot().to_string(), "  ab\n  de\n    ");
+
+        let mut dst = CellBuffer::with_layers((4, 3), 1, *colors::BLACK);
+        blit(&source(), Rect::with_dimensions((3, 2)), &mut dst, (-2, -1), 1.0, 1.0);
+        assert_eq!(dst.snapshot().to_string(), "    \n    \nc   ");
+    }
+
+    #[test]
+    fn clips_the_source_rect_to_the_source() {
+        let mut dst = CellBuffer::with_layers((4, 3), 1, *colors::BLACK);
+        blit(&source(), Rect::new(2, 1, 5, 5), &mut dst, (0, 0), 1.0, 1.0);
+        assert_eq!(dst.snapshot().to_string(), "    \n    \nc   ");
+    }
+
+    #[test]
+    fn empty_source_cells_leave_the_destination_alone() {
+        let mut src = CellBuffer::with_layers((2, 1), 1, *colors::BLACK);
+        src.put_char('x', 1, 0, *colors::WHITE, None, 0);
+        let mut dst = CellBuffer::with_layers((2, 1), 1, *colors::BLACK);
+        dst.put_text("ab", 0, 0, *colors::WHITE, None, 0);
+        blit(&src, Rect::with_dimensions((2, 1)), &mut dst, (0, 0), 1.0, 1.0);
+        assert_eq!(dst.row_text(0), "ax");
+    }
+
+    #[test]
+    fn alpha_blends_with_the_destination() {
+        let mut dst = CellBuffer::with_layers((3, 2), 1, *colors::BLACK);
+        dst.put_text("abc", 0, 1, *colors::BLACK, Some(*colors::BLACK), 0);
+        blit(&source(), Rect::with_dimensions((3, 2)), &mut dst, (0, 0), 0.5, 0.25);
+        let glyph = dst.get_cell(0, 1, 0).unwrap();
+        assert_eq!((glyph.character, glyph.foreground, glyph.background), ('a', grey(0.5), grey(0.25)));
+    }
+
+    #[test]
+    fn empty_destination_cells_blend_with_what_shows_through() {
+        let mut dst = CellBuffer::with_layers((3, 2), 2, *colors::BLACK);
+        dst.put_char(' ', 1, 0, *colors::WHITE, Some(*colors::WHITE), 0);
+        let mut src = CellBuffer::with_layers((3, 2), 2, *colors::BLACK);
+        src.put_text("xyz", 0, 0, *colors::WHITE, Some(*colors::WHITE), 1);
+        blit(&src, Rect::with_dimensions((3, 2)), &mut dst, (0, 0), 1.0, 0.5);
+
+        // Over the default background
+        let glyph = dst.get_cell(0, 0, 1).unwrap();
+        assert_eq!((glyph.character, glyph.foreground, glyph.background), ('x', grey(1.0), grey(0.5)));
+        // Over the white cell on the layer below
+        assert_eq!(dst.get_cell(1, 0, 1).unwrap().background, grey(1.0));
+    }
+}
@@ -0,0 +1,565 @@
+use std::fmt;
+use crate::glyph::Glyph;
+use crate::rect::Rect;
+use crate::style::Style;
+use crate::markup::Span;
+use crate::text_layout::{Align, layout_spans};
+use crate::frame::FrameStyle;
+use crate::Color;
+
+// Cells touched since the last render, so backends only rewrite what changed
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Damage {
+    Clean,
+    Region { min: (u32, u32), max: (u32, u32) },
+    Full,
+}
+
+impl Damage {
+    pub fn contains(&self, position: (u32, u32)) -> bool {
+        match self {
+            Damage::Clean => false,
+            Damage::Region { min, max } =>
+                position.0 >= min.0 && position.0 <= max.0 && position.1 >= min.1 && position.1 <= max.1,
+            Damage::Full => true,
+        }
+    }
+
+    fn include(self, position: (u32, u32)) -> Damage {
+        match self {
+            Damage::Clean => Damage::Region { min: position, max: position },
+            Damage::Region { min, max } => Damage::Region {
+                min: (min.0.min(position.0), min.1.min(position.1)),
+                max: (max.0.max(position.0), max.1.max(position.1)),
+            },
+            Damage::Full => Damage::Full,
+        }
+    }
+}
+
+pub const DEFAULT_LAYERS: u32 = 4;
+
+// GL-free storage for everything drawn on a console, one dense grid per layer
+pub struct CellBuffer {
+    dimensions: (u32, u32),
+    layers: Vec<Vec<Option<Glyph>>>,
+    default_background: Color,
+    damage: Damage,
+}
+
+impl CellBuffer {
+    pub fn new(dimensions: (u32, u32), default_background: Color) -> Self {
+        CellBuffer::with_layers(dimensions, DEFAULT_LAYERS, default_background)
+    }
+
+    pub fn with_layers(dimensions: (u32, u32), layers: u32, default_background: Color) -> Self {
+        let cells = (dimensions.0 * dimensions.1) as usize;
+        CellBuffer {
+            dimensions,
+            layers: vec![vec![None; cells]; layers as usize],
+            default_background,
+            damage: Damage::Full,
+        }
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    // Cells keep their coordinates, the ones outside the new size are dropped
+    pub fn resize(&mut self, dimensions: (u32, u32)) {
+        self.resize_shifted(dimensions, (0, 0));
+    }
+
+    // Like resize, but every cell is moved by offset first
+    pub fn resize_shifted(&mut self, dimensions: (u32, u32), offset: (i32, i32)) {
+        if dimensions == self.dimensions && offset == (0, 0) {
+            return;
+        }
+        let cells = (dimensions.0 * dimensions.1) as usize;
+        let old_dimensions = self.dimensions;
+        for layer in self.layers.iter_mut() {
+            let mut resized = vec![None; cells];
+            for y in 0..old_dimensions.1 {
+                for x in 0..old_dimensions.0 {
+                    let (new_x, new_y) = (x as i32 + offset.0, y as i32 + offset.1);
+                    if new_x < 0 || new_y < 0 || new_x as u32 >= dimensions.0 || new_y as u32 >= dimensions.1 {
+                        continue;
+                    }
+                    resized[(new_x as u32 + new_y as u32 * dimensions.0) as usize] = layer[(x + y * old_dimensions.0) as usize];
+                }
+            }
+            *layer = resized;
+        }
+        self.dimensions = dimensions;
+        self.damage = Damage::Full;
+    }
+
+    // Overwrites every cell both buffers have with other's, empty ones included
+    pub(crate) fn copy_from(&mut self, other: &CellBuffer) {
+        let width = self.dimensions.0.min(other.dimensions.0);
+        let height = self.dimensions.1.min(other.dimensions.1);
+        for (layer, other_layer) in self.layers.iter_mut().zip(other.layers.iter()) {
+            for y in 0..height {
+                for x in 0..width {
+                    layer[(x + y * self.dimensions.0) as usize] = other_layer[(x + y * other.dimensions.0) as usize];
+                }
+            }
+        }
+        self.damage = Damage::Full;
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        self.layers.len() as u32
+    }
+
+    pub fn default_background(&self) -> Color {
+        self.default_background
+    }
+
+    pub fn fill_background(&mut self, background: Color) {
+        for x in 0..self.dimensions.0 {
+            for y in 0..self.dimensions.1 {
+                self.put_char(' ', x as i32, y as i32, background, Some(background), 1);
+            }
+        }
+    }
+
+    // Only the cells that had something drawn in them are damaged
+    pub fn clear(&mut self) {
+        let width = self.dimensions.0;
+        let mut damage = self.damage;
+        for layer in self.layers.iter_mut() {
+            for (index, cell) in layer.iter_mut().enumerate() {
+                if cell.take().is_some() {
+                    damage = damage.include((index as u32 % width, index as u32 / width));
+                }
+            }
+        }
+        self.damage = damage;
+    }
+
+    pub fn put_text(&mut self, text: &str, x: i32, y: i32, foreground: Color, background: Option<Color>, layer: u32) {
+        self.put_text_styled(text, x, y, foreground, background, Style::NONE, layer);
+    }
+
+    pub fn put_text_styled(&mut self, text: &str, x: i32, y: i32, foreground: Color, background: Option<Color>, style: Style, layer: u32) {
+        for (index, c) in text.chars().enumerate() {
+            self.put_char_styled(c, x + index as i32, y, foreground, background, style, layer);
+        }
+    }
+
+    // Spans without their own colors use foreground and background. Returns the width
+    // printed in cells.
+    pub fn put_spans(&mut self, spans: &[Span], x: i32, y: i32, foreground: Color, background: Option<Color>, layer: u32) -> u32 {
+        let mut width = 0;
+        for span in spans {
+            let span_background = span.background.or(background);
+            let span_foreground = span.foreground.unwrap_or(foreground);
+            self.put_text_styled(&span.text, x + width as i32, y, span_foreground, span_background, span.style, layer);
+            width += span.len() as u32;
+        }
+        width
+    }
+
+    // Prints text inside rect starting from its top row, breaking lines at newlines and, if
+    // wrap is set, between words. Whatever doesn't fit in the rect is cut off. Returns the
+    // number of rows printed.
+    pub fn print_rect(&mut self, rect: Rect, text: &str, align: Align, wrap: bool, foreground: Color, background: Option<Color>, layer: u32) -> u32 {
+        self.print_rect_spans(rect, &[Span::new(text)], align, wrap, foreground, background, layer)
+    }
+
+    pub fn print_rect_spans(&mut self, rect: Rect, spans: &[Span], align: Align, wrap: bool, foreground: Color, background: Option<Color>, layer: u32) -> u32 {
+        if rect.is_empty() {
+            return 0;
+        }
+        let lines = layout_spans(spans, rect.width, wrap);
+        let rows = (lines.len() as u32).min(rect.height);
+        for (row, line) in lines.iter().take(rows as usize).enumerate() {
+            let mut line = line.clone();
+            let mut room = rect.width as usize;
+            for span in line.iter_mut() {
+                span.text = span.text.chars().take(room).collect();
+                room -= span.len();
+            }
+            let length = rect.width as usize - room;
+            let x = rect.x + align.offset(length as u32, rect.width);
+            self.put_spans(&line, x as i32, (rect.top() - row as u32) as i32, foreground, background, layer);
+        }
+        rows
+    }
+
+    // length cells to the right of x, y
+    pub fn hline(&mut self, x: i32, y: i32, length: u32, c: char, foreground: Color, background: Option<Color>, layer: u32) {
+        for offset in 0..length as i32 {
+            self.put_char(c, x + offset, y, foreground, background, layer);
+        }
+    }
+
+    // length cells up from x, y
+    pub fn vline(&mut self, x: i32, y: i32, length: u32, c: char, foreground: Color, background: Option<Color>, layer: u32) {
+        for offset in 0..length as i32 {
+            self.put_char(c, x, y + offset, foreground, background, layer);
+        }
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, c: char, foreground: Color, background: Option<Color>, layer: u32) {
+        for y in rect.y..rect.y + rect.height {
+            self.hline(rect.x as i32, y as i32, rect.width, c, foreground, background, layer);
+        }
+    }
+
+    // Outlines rect, leaving the inside alone. The title goes centered into the top edge between
+    // the corners, cut off at its end if it's longer. Rects under 2x2 are left empty.
+    pub fn draw_frame(&mut self, rect: Rect, style: FrameStyle, title: Option<&str>, foreground: Color, background: Option<Color>, layer: u32) {
+        if rect.width < 2 || rect.height < 2 {
+            return;
+        }
+        let chars = style.chars();
+        let (left, bottom, right, top) = (rect.x as i32, rect.y as i32, rect.right() as i32, rect.top() as i32);
+        self.hline(left + 1, bottom, rect.width - 2, chars.horizontal, foreground, background, layer);
+        self.hline(left + 1, top, rect.width - 2, chars.horizontal, foreground, background, layer);
+        self.vline(left, bottom + 1, rect.height - 2, chars.vertical, foreground, background, layer);
+        self.vline(right, bottom + 1, rect.height - 2, chars.vertical, foreground, background, layer);
+        self.put_char(chars.bottom_left, left, bottom, foreground, background, layer);
+        self.put_char(chars.bottom_right, right, bottom, foreground, background, layer);
+        self.put_char(chars.top_left, left, top, foreground, background, layer);
+        self.put_char(chars.top_right, right, top, foreground, background, layer);
+        if let Some(title) = title {
+            let edge = Rect::new(rect.x + 1, rect.top(), rect.width - 2, 1);
+            self.print_rect(edge, title, Align::Center, false, foreground, background, layer);
+        }
+    }
+
+    // A bar filled from the left to fraction of its width with filled, the rest with empty.
+    // Only the backgrounds are set, so a label can be printed on top. Fractions outside 0 to 1
+    // are clamped, NaN counts as 0.
+    pub fn draw_progress_bar(&mut self, rect: Rect, fraction: f32, filled: Color, empty: Color, layer: u32) {
+        let fraction = fraction.max(0.0).min(1.0);
+        let filled_width = (fraction * rect.width as f32).round() as u32;
+        self.fill_rect(Rect::new(rect.x, rect.y, filled_width, rect.height), ' ', filled, Some(filled), layer);
+        self.fill_rect(Rect::new(rect.x + filled_width, rect.y, rect.width - filled_width, rect.height), ' ', empty, Some(empty), layer);
+    }
+
+    // Anything outside the grid or past the last layer is dropped
+    pub fn put_char(&mut self, c: char, x: i32, y: i32, foreground: Color, background: Option<Color>, layer: u32) {
+        self.put_char_styled(c, x, y, foreground, background, Style::NONE, layer);
+    }
+
+    pub fn put_char_styled(&mut self, c: char, x: i32, y: i32, foreground: Color, background: Option<Color>, style: Style, layer: u32) {
+        if !self.contains(x, y) || layer >= self.layer_count() {
+            return
+        }
+        let background = match background {
+            Some(b) => b,
+            None => self.default_background
+        };
+        let index = self.coordinates_to_index(x as u32, y as u32);
+        let glyph = Some(Glyph::styled(c, background, foreground, style));
+        let cell = &mut self.layers[layer as usize][index];
+        if *cell != glyph {
+            *cell = glyph;
+            self.damage = self.damage.include((x as u32, y as u32));
+        }
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as u32) < self.dimensions.0 && (y as u32) < self.dimensions.1
+    }
+
+    pub fn get_cell(&self, x: u32, y: u32, layer: u32) -> Option<&Glyph> {
+        if x >= self.dimensions.0 || y >= self.dimensions.1 {
+            return None;
+        }
+        self.layers.get(layer as usize)
+            .and_then(|cells| cells[self.coordinates_to_index(x, y)].as_ref())
+    }
+
+    // Left to right
+    pub fn row(&self, y: u32, layer: u32) -> impl Iterator<Item = Option<&Glyph>> {
+        (0..self.dimensions.0).map(move |x| self.get_cell(x, y, layer))
+    }
+
+    // Bottom to top
+    pub fn column(&self, x: u32, layer: u32) -> impl Iterator<Item = Option<&Glyph>> {
+        (0..self.dimensions.1).map(move |y| self.get_cell(x, y, layer))
+    }
+
+    // The glyph on the highest layer that has one, with its layer
+    pub fn topmost_cell(&self, x: u32, y: u32) -> Option<(u32, &Glyph)> {
+        (0..self.layer_count()).rev()
+            .filter_map(|layer| self.get_cell(x, y, layer).map(|glyph| (layer, glyph)))
+            .next()
+    }
+
+    // What shows through a transparent cell: the topmost opaque background under layer, else the
+    // default one
+    pub(crate) fn background_below(&self, x: u32, y: u32, layer: u32) -> Color {
+        (0..layer).rev()
+            .filter_map(|below| self.get_cell(x, y, below))
+            .map(|glyph| glyph.background)
+            .find(|background| background.a >= 0.1)
+            .unwrap_or(self.default_background)
+    }
+
+    // Topmost characters of a row, empty cells read as spaces
+    pub fn row_text(&self, y: u32) -> String {
+        self.rect_text(Rect::new(0, y, self.dimensions.0, 1))
+    }
+
+    // Topmost characters inside the rect, one line per row starting from the top
+    pub fn rect_text(&self, rect: Rect) -> String {
+        let rect = rect.intersection(&Rect::with_dimensions(self.dimensions));
+        if rect.is_empty() {
+            return String::new();
+        }
+        (rect.y..=rect.top()).rev()
+            .map(|y| (rect.x..=rect.right())
+                .map(|x| self.topmost_cell(x, y).map_or(' ', |(_, glyph)| glyph.character))
+                .collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            dimensions: self.dimensions,
+            layers: self.layers.clone(),
+        }
+    }
+
+    // ((x, y), layer, glyph) for every cell that has been drawn, lowest layer first and
+    // then row by row from the bottom left, so the order is always the same
+    pub fn iter(&self) -> impl Iterator<Item = ((u32, u32), u32, &Glyph)> {
+        self.layers.iter().enumerate().flat_map(move |(layer, cells)| {
+            cells.iter().enumerate().filter_map(move |(index, cell)| {
+                cell.as_ref().map(|glyph| (self.index_to_coordinates(index), layer as u32, glyph))
+            })
+        })
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.damage != Damage::Clean
+    }
+
+    pub fn damage(&self) -> Damage {
+        self.damage
+    }
+
+    pub fn set_dirty(&mut self) {
+        self.damage = Damage::Full;
+    }
+
+    pub(crate) fn clear_dirty(&mut self) {
+        self.damage = Damage::Clean;
+    }
+
+    pub(crate) fn coordinates_to_index(&self, x: u32, y: u32) -> usize {
+        (x + y * self.dimensions.0) as usize
+    }
+
+    fn index_to_coordinates(&self, index: usize) -> (u32, u32) {
+        let index = index as u32;
+        (index % self.dimensions.0, index / self.dimensions.0)
+    }
+}
+
+// Copy of every layer of a buffer at one point in time, compares equal when the buffers
+// would render the same way
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    dimensions: (u32, u32),
+    layers: Vec<Vec<Option<Glyph>>>,
+}
+
+impl Snapshot {
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    pub fn get_cell(&self, x: u32, y: u32, layer: u32) -> Option<&Glyph> {
+        if x >= self.dimensions.0 || y >= self.dimensions.1 {
+            return None;
+        }
+        self.layers.get(layer as usize)
+            .and_then(|cells| cells[(x + y * self.dimensions.0) as usize].as_ref())
+    }
+}
+
+// Topmost characters, top row first, which makes for readable assertion failures
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in (0..self.dimensions.1).rev() {
+            for x in 0..self.dimensions.0 {
+                let character = (0..self.layers.len() as u32).rev()
+                    .filter_map(|layer| self.get_cell(x, y, layer))
+                    .map(|glyph| glyph.character)
+                    .next()
+                    .unwrap_or(' ');
+                write!(f, "{}", character)?;
+            }
+            if y > 0 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors;
+
+    fn buffer() -> CellBuffer {
+        CellBuffer::new((4, 2), *colors::BLACK)
+    }
+
+    #[test]
+    fn snapshots_compare_every_layer() {
+        let mut cells = buffer();
+        cells.put_text("ab", 0, 1, *colors::WHITE, None, 1);
+        let before = cells.snapshot();
+        assert_eq!(before, cells.snapshot());
+
+        // Hidden under the same character it changes nothing on screen but still counts
+        cells.put_char('a', 0, 1, *colors::WHITE, None, 0);
+        assert_ne!(before, cells.snapshot());
+        assert_eq!(before.to_string(), cells.snapshot().to_string());
+    }
+
+    #[test]
+    fn snapshots_print_the_top_row_first() {
+        let mut cells = buffer();
+        cells.put_text("top", 0, 1, *colors::WHITE, None, 1);
+        cells.put_text("bot", 1, 0, *colors::WHITE, None, 1);
+        assert_eq!(cells.snapshot().to_string(), "top \n bot");
+    }
+
+    #[test]
+    fn row_text_reads_the_topmost_characters() {
+        let mut cells = buffer();
+        cells.put_text("abcd", 0, 0, *colors::WHITE, None, 1);
+        cells.put_char('X', 1, 0, *colors::WHITE, None, 3);
+        cells.put_char('Y', 2, 0, *colors::WHITE, None, 0);
+        assert_eq!(cells.row_text(0), "aXcd");
+        assert_eq!(cells.row_text(1), "    ");
+        assert_eq!(cells.row_text(2), "");
+    }
+
+    #[test]
+    fn topmost_cell_skips_empty_layers() {
+        let mut cells = buffer();
+        cells.put_char('a', 0, 0, *colors::WHITE, None, 0);
+        cells.put_char('b', 0, 0, *colors::WHITE, None, 2);
+        let (layer, glyph) = cells.topmost_cell(0, 0).unwrap();
+        assert_eq!((layer, glyph.character), (2, 'b'));
+        assert!(cells.topmost_cell(1, 0).is_none());
+        assert!(cells.topmost_cell(9, 9).is_none());
+    }
+
+    #[test]
+    fn clear_only_damages_drawn_cells() {
+        let mut cells = buffer();
+        cells.put_char('a', 1, 0, *colors::WHITE, None, 0);
+        cells.put_char('b', 2, 1, *colors::WHITE, None, 2);
+        cells.clear_dirty();
+        cells.clear();
+        assert_eq!(cells.damage(), Damage::Region { min: (1, 0), max: (2, 1) });
+        cells.clear_dirty();
+        cells.clear();
+        assert_eq!(cells.damage(), Damage::Clean);
+    }
+
+    #[test]
+    fn print_rect_aligns_every_line() {
+        let mut cells = CellBuffer::new((6, 3), *colors::BLACK);
+        let rect = Rect::with_dimensions((6, 3));
+        cells.print_rect(rect, "ab", Align::Left, true, *colors::WHITE, None, 1);
+        cells.print_rect(Rect::new(0, 1, 6, 1), "cd", Align::Center, true, *colors::WHITE, None, 1);
+        cells.print_rect(Rect::new(0, 0, 6, 1), "ef", Align::Right, true, *colors::WHITE, None, 1);
+        assert_eq!(cells.snapshot().to_string(), "ab    \n  cd  \n    ef");
+    }
+
+    #[test]
+    fn print_rect_cuts_off_what_does_not_fit() {
+        let mut cells = CellBuffer::new((6, 3), *colors::BLACK);
+        let rows = cells.print_rect(Rect::new(1, 1, 4, 2), "one two three", Align::Left, true, *colors::WHITE, None, 1);
+        assert_eq!(rows, 2);
+        assert_eq!(cells.snapshot().to_string(), " one  \n two  \n      ");
+
+        let mut cells = CellBuffer::new((6, 1), *colors::BLACK);
+        let rows = cells.print_rect(Rect::new(0, 0, 4, 1), "one two", Align::Right, false, *colors::WHITE, None, 1);
+        assert_eq!(rows, 1);
+        assert_eq!(cells.row_text(0), "one   ");
+        assert_eq!(cells.print_rect(Rect::new(0, 0, 0, 1), "x", Align::Left, true, *colors::WHITE, None, 1), 0);
+    }
+
+    #[test]
+    fn frames_put_corners_at_the_rect_corners() {
+        let mut cells = CellBuffer::new((5, 4), *colors::BLACK);
+        cells.put_char('x', 2, 1, *colors::WHITE, None, 0);
+        cells.draw_frame(Rect::new(1, 0, 4, 3), FrameStyle::Single, None, *colors::WHITE, None, 0);
+        assert_eq!(cells.snapshot().to_string(), "     \n ┌──┐\n │x │\n └──┘");
+        cells.draw_frame(Rect::new(0, 2, 2, 2), FrameStyle::Ascii, None, *colors::WHITE, None, 1);
+        assert_eq!(cells.rect_text(Rect::new(0, 2, 2, 2)), "++\n++");
+    }
+
+    #[test]
+    fn frame_titles_are_centered_and_cut_to_fit() {
+        let mut cells = CellBuffer::new((8, 2), *colors::BLACK);
+        cells.draw_frame(Rect::with_dimensions((8, 2)), FrameStyle::Double, Some("ab"), *colors::WHITE, None, 0);
+        assert_eq!(cells.row_text(1), "╔══ab══╗");
+
+        let mut cells = CellBuffer::new((6, 2), *colors::BLACK);
+        cells.draw_frame(Rect::with_dimensions((6, 2)), FrameStyle::Single, Some("Title!!"), *colors::WHITE, None, 0);
+        assert_eq!(cells.row_text(1), "┌Titl┐");
+    }
+
+    #[test]
+    fn zero_sized_rects_draw_nothing() {
+        let mut cells = CellBuffer::new((3, 3), *colors::BLACK);
+        cells.draw_frame(Rect::new(0, 0, 1, 3), FrameStyle::Single, Some("t"), *colors::WHITE, None, 0);
+        cells.draw_frame(Rect::new(0, 0, 3, 0), FrameStyle::Single, None, *colors::WHITE, None, 0);
+        cells.fill_rect(Rect::new(1, 1, 0, 2), '#', *colors::WHITE, None, 0);
+        cells.hline(0, 0, 0, '-', *colors::WHITE, None, 0);
+        cells.vline(0, 0, 0, '|', *colors::WHITE, None, 0);
+        cells.draw_progress_bar(Rect::new(0, 0, 0, 1), 0.5, *colors::WHITE, *colors::BLACK, 0);
+        assert_eq!(cells.iter().count(), 0);
+    }
+
+    #[test]
+    fn lines_and_fills_stop_at_the_edges() {
+        let mut cells = CellBuffer::new((3, 3), *colors::BLACK);
+        cells.hline(1, 0, 5, '-', *colors::WHITE, None, 0);
+        cells.vline(0, 1, 5, '|', *colors::WHITE, None, 0);
+        cells.fill_rect(Rect::new(2, 2, 4, 4), '#', *colors::WHITE, None, 0);
+        assert_eq!(cells.snapshot().to_string(), "| #\n|  \n --");
+    }
+
+    #[test]
+    fn progress_bars_clamp_their_fraction() {
+        let filled_width = |fraction: f32| {
+            let mut cells = CellBuffer::new((4, 1), *colors::BLACK);
+            cells.draw_progress_bar(Rect::new(0, 0, 4, 1), fraction, *colors::WHITE, *colors::NAVY, 0);
+            assert_eq!(cells.iter().count(), 4);
+            cells.row(0, 0).filter(|glyph| glyph.unwrap().background == *colors::WHITE).count()
+        };
+        assert_eq!(filled_width(0.0), 0);
+        assert_eq!(filled_width(0.5), 2);
+        assert_eq!(filled_width(1.0), 4);
+        assert_eq!(filled_width(-1.0), 0);
+        assert_eq!(filled_width(7.5), 4);
+        assert_eq!(filled_width(std::f32::NAN), 0);
+    }
+
+    #[test]
+    fn resize_keeps_cells_where_they_were() {
+        let mut cells = buffer();
+        cells.put_text("abcd", 0, 0, *colors::WHITE, None, 1);
+        cells.resize((2, 3));
+        assert_eq!(cells.snapshot().to_string(), "  \n  \nab");
+        cells.resize_shifted((3, 3), (1, 1));
+        assert_eq!(cells.snapshot().to_string(), "   \n ab\n   ");
+    }
+}
@@ -26,4 +26,10 @@ impl Color {
     pub fn from_int(x: i32, y: i32, z: i32, a: f32) -> Color {
         data::f32_f32_f32_f32::new(x as f32 / 255.0, y as f32 / 255.0, z as f32 / 255.0, a as f32)
     }
-}
\ No newline at end of file
+
+    // Moves amount of the way from self to other, alpha included
+    pub fn lerp(self, other: Color, amount: f32) -> Color {
+        let mix = |from: f32, to: f32| from + (to - from) * amount;
+        data::f32_f32_f32_f32::new(mix(self.x, other.x), mix(self.y, other.y), mix(self.z, other.z), mix(self.a, other.a))
+    }
+}
@@ -1,131 +1,195 @@
 use crate::resources::Resources;
-use font_renderer::{load_bitmap, BoundingBox};
-use crate::{render_gl, Color};
-use image::{GenericImageView, DynamicImage};
-use crate::render_gl::texture::Texture;
-use crate::render_gl::buffer::{VertexArray, ArrayBuffer, ElementArrayBuffer};
-use std::collections::HashMap;
-use crate::render_gl::{Program, data};
-use failure::_core::cell::RefCell;
-use crate::console_vertex::Vertex;
+use crate::assets::Assets;
+use crate::Color;
+use crate::render_gl::data;
+use crate::backend::{RenderBackend, HeadlessBackend, Placement};
+use crate::cell_buffer::{CellBuffer, Snapshot, DEFAULT_LAYERS};
 use crate::glyph::Glyph;
-use glutin::{
-    dpi::LogicalSize,
-};
-use core::ptr;
+use crate::style::Style;
+use crate::markup::{Span, Palette, parse_markup};
+use crate::text_layout::Align;
+use crate::frame::FrameStyle;
+use crate::rect::Rect;
+use crate::layout::{Anchor, Layout, Length, Region};
+use crate::export;
+use crate::blit::blit;
+use crate::rexpaint::{write_xp, XpError};
+use crate::font_info::{FontInfo, DEFAULT_FONT};
+use crate::font_registry::{FontRegistry, FontKey};
+use crate::render_gl::glyph_renderer::GlyphRenderer;
 use crate::color::colors;
-use nalgebra_glm::scale;
+use font_renderer::{Charset, TilesetLayout, CP437};
 
+// Adapts consoles to the window. Each one starts over from the console's own layout, so
+// they only combine with Letterbox going first.
 pub enum Transformer {
-    AspectRatio(f32, f32),
+    // Keeps consoles at this width to height ratio whatever shape the window has
+    AspectRatio(f32),
+    // Draws cells at a whole multiple of the font's atlas cell size so pixel art stays crisp,
+    // or a whole fraction of it for fonts rasterized bigger than they're shown
+    IntegerScale,
+    // Lays consoles out in the biggest centered box of this ratio, the bars around it are
+    // left to be cleared with the color, see ConsoleRoot::bar_color. Consoles placed
+    // relative to another one keep their place inside it.
+    Letterbox(f32, Color),
+    // Cells stay this many pixels big, consoles gain or lose cells as the window changes
+    FitCells((f32, f32)),
 }
 
 impl Transformer {
-    pub fn apply(&self, console: &mut Console) -> &Self {
+    pub fn apply(&self, console: &mut Console, window_size: (f32, f32)) -> &Self {
+        console.placement.scale_modifier = (1.0, 1.0);
+        console.pixel_grid = None;
+        console.root = Region::screen();
         match self {
-            Transformer::AspectRatio(desired, actual ) => Transformer::apply_aspect_ratio(console, *desired, *actual)
+            Transformer::AspectRatio(desired) => Transformer::apply_aspect_ratio(console, *desired, window_size.0 / window_size.1),
+            Transformer::IntegerScale => Transformer::apply_integer_scale(console, window_size),
+            Transformer::Letterbox(desired, _) => Transformer::apply_letterbox(console, *desired, window_size.0 / window_size.1),
+            Transformer::FitCells(cell_size) => Transformer::apply_fit_cells(console, *cell_size, window_size),
         }
+        console.resolve_layout();
         console.set_dirty();
         self
     }
 
     fn apply_aspect_ratio(console: &mut Console, desired: f32, actual: f32) {
-        console.scale_modifier = (1.0, 1.0);
+        let scale_modifier = &mut console.placement.scale_modifier;
         if desired > actual {
-            console.scale_modifier.1 = actual / desired;
+            scale_modifier.1 = actual / desired;
         } else {
-            console.scale_modifier.0 = desired / actual;
+            scale_modifier.0 = desired / actual;
         }
-        println!("Scaling {:?}", console.scale_modifier);
     }
-}
-
-#[derive(Clone)]
-struct FontInfo {
-    texture: Texture,
-    glyph_map: HashMap<char, BoundingBox>,
-    texture_scale: (i32, i32),
-}
-
-pub struct Console {
-    is_dirty: RefCell<Dirty>,
-    num_vert: RefCell<Num>,
-    vao: VertexArray,
-    vbo: ArrayBuffer,
-    ebo: ElementArrayBuffer,
-    font_info: FontInfo,
-    glyphs: HashMap<(u32, u32), Glyph>,
-    program: Program,
-    dimensions: (u32, u32),
-    screen_scaling:(f32, f32),
-    height: u32,
-    screen_offset: (f32, f32),
-    default_background: data::f32_f32_f32_f32,
-    scale_modifier: (f32, f32),
-}
 
-struct Num(i32);
+    fn apply_integer_scale(console: &mut Console, window_size: (f32, f32)) {
+        let cell_size = match console.backend.cell_size() {
+            Some(cell_size) => cell_size,
+            None => return,
+        };
+        let available = console.available_pixels(window_size);
+        let dimensions = console.dimensions();
+        let native = (dimensions.0 as f32 * cell_size.0, dimensions.1 as f32 * cell_size.1);
+        let fit = (available.0 / native.0).min(available.1 / native.1);
+        let factor = if fit >= 1.0 { fit.floor() } else { 1.0 / (1.0 / fit).ceil() };
+        console.placement.scale_modifier = (native.0 * factor / available.0, native.1 * factor / available.1);
+        console.pixel_grid = Some(window_size);
+    }
+
+    fn apply_letterbox(console: &mut Console, desired: f32, actual: f32) {
+        let half_size = if desired > actual {
+            (1.0, actual / desired)
+        } else {
+            (desired / actual, 1.0)
+        };
+        console.root = Region {
+            center: (0.0, 0.0),
+            half_size,
+        };
+    }
 
-impl Num {
-    pub fn set(&mut self, v: i32) {
-        self.0 = v;
+    fn apply_fit_cells(console: &mut Console, cell_size: (f32, f32), window_size: (f32, f32)) {
+        let available = console.available_pixels(window_size);
+        let dimensions = (((available.0 / cell_size.0).floor() as u32).max(1),
+                          ((available.1 / cell_size.1).floor() as u32).max(1));
+        console.resize(dimensions, ResizePolicy::Preserve);
+        console.placement.scale_modifier = (dimensions.0 as f32 * cell_size.0 / available.0,
+                                            dimensions.1 as f32 * cell_size.1 / available.1);
     }
 }
 
-struct Dirty(bool);
-
-impl Dirty {
-    pub fn set(&mut self, v: bool) {
-        self.0 = v;
-    }
+// What happens to a console's content when it gains or loses cells
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ResizePolicy {
+    // Cells keep their coordinates, the ones that no longer fit come back if the console grows
+    // again before it's cleared
+    Preserve,
+    // Cells keep their coordinates, the ones that no longer fit are dropped
+    Clip,
+    // Content stays stuck to this point of the console, Top keeping the top rows in view
+    Reanchor(Anchor),
 }
 
-struct RelativeConsole {
-    scale: (f32, f32),
-    offset: (f32, f32),
+// Called after a console changed size with its old dimensions, so whatever draws on it can
+// lay its content out again
+pub type ResizeHook = Box<dyn FnMut(&mut Console, (u32, u32))>;
+
+pub struct Console {
+    cells: CellBuffer,
+    // Cells a Preserve resize pushed out
+    retained: Option<CellBuffer>,
+    resize_hook: Option<ResizeHook>,
+    backend: Box<dyn RenderBackend>,
+    placement: Placement,
+    layout: Layout,
+    // None for consoles laid out against the root region rather than another console
+    parent: Option<Region>,
+    // The whole window, unless a letterbox shrinks it
+    root: Region,
+    visible: bool,
+    // Window size in pixels the console's corner is kept on whole pixels of
+    pixel_grid: Option<(f32, f32)>,
 }
 
 pub struct ConsoleBuilder {
     size: (u32, u32),
     scale: (f32, f32),
-    offset: (f32, f32),
+    anchor: Anchor,
+    margin: (Length, Length),
+    parent: Option<Region>,
     layer: u32,
+    layer_count: u32,
     background: Color,
     font: String,
-    relative: Option<RelativeConsole>,
-    centered: bool,
     font_size: Option<(f32, f32)>,
     font_info: Option<FontInfo>,
+    replacement_glyph: Option<char>,
+    charset: Charset,
+    glyph_cache: bool,
+    sdf: bool,
+    tileset_layout: TilesetLayout,
+    codepage: Vec<char>,
+    fonts: Option<FontRegistry>,
 }
 
-impl ConsoleBuilder {
-    pub fn with_dimensions(size: (u32, u32)) -> Self {
+// A one cell console covering its parent, the constructors start from it
+impl Default for ConsoleBuilder {
+    fn default() -> Self {
         ConsoleBuilder {
-            size,
+            size: (1, 1),
             scale: (1.0, 1.0),
-            offset: (0.0, 0.0),
+            anchor: Anchor::BottomLeft,
+            margin: (Length::Cells(0.0), Length::Cells(0.0)),
+            parent: None,
             layer: 1,
+            layer_count: DEFAULT_LAYERS,
             background: *colors::BLACK,
-            font: "ubuntu-mono-regular.ttf".to_string(),
-            relative: None,
-            centered: false,
+            font: DEFAULT_FONT.to_string(),
             font_size: None,
             font_info: None,
+            replacement_glyph: None,
+            charset: Charset::default(),
+            glyph_cache: true,
+            sdf: false,
+            tileset_layout: TilesetLayout::default(),
+            codepage: CP437.to_vec(),
+            fonts: None,
+        }
+    }
+}
+
+impl ConsoleBuilder {
+    pub fn with_dimensions(size: (u32, u32)) -> Self {
+        ConsoleBuilder {
+            size,
+            ..Default::default()
         }
     }
 
     pub fn with_font_size(size: (f32, f32)) -> Self {
         ConsoleBuilder {
             size: ((1.0 / size.0) as u32 , (1.0 / size.1) as u32),
-            scale: (1.0, 1.0),
-            offset: (0.0, 0.0),
-            layer: 1,
-            background: *colors::BLACK,
-            font: "ubuntu-mono-regular.ttf".to_string(),
-            relative: None,
-            centered: false,
             font_size: Some(size),
-            font_info: None,
+            ..Default::default()
         }
     }
 
@@ -133,14 +197,8 @@ impl ConsoleBuilder {
         ConsoleBuilder {
             size: dimensions,
             scale: (font_size.0 * dimensions.0 as f32, font_size.1 * dimensions.1 as f32),
-            offset: (0.0, 0.0),
-            layer: 1,
-            background: *colors::BLACK,
-            font: "ubuntu-mono-regular.ttf".to_string(),
-            relative: None,
-            centered: false,
             font_size: Some(font_size),
-            font_info: None,
+            ..Default::default()
         }
     }
 
@@ -163,9 +221,15 @@ impl ConsoleBuilder {
         self
     }
 
-    // relative to bottom left
-    pub fn offset(&mut self, offset: (f32, f32)) -> &mut Self {
-        self.offset = offset;
+    // The point of the parent the console is attached to, the bottom left by default
+    pub fn anchor(&mut self, anchor: Anchor) -> &mut Self {
+        self.anchor = anchor;
+        self
+    }
+
+    // Distance from the anchored edges
+    pub fn margin(&mut self, margin: (Length, Length)) -> &mut Self {
+        self.margin = margin;
         self
     }
 
@@ -174,253 +238,550 @@ impl ConsoleBuilder {
         self
     }
 
+    // Number of cell layers, put_char ignores layers past this
+    pub fn layer_count(&mut self, layer_count: u32) -> &mut Self {
+        self.layer_count = layer_count;
+        self
+    }
+
     pub fn background(&mut self, background: Color) -> &mut Self {
         self.background = background;
         self
     }
 
+    // A TTF, or a tileset image cut up as set with tileset
     pub fn font(&mut self, font: &str) -> &mut Self {
         self.font = font.to_string();
         self
     }
 
-    pub fn centered(&mut self, centered: bool) -> &mut Self {
-        self.centered = centered;
+    // Drawn for characters the font doesn't have, '?' unless set. Baked atlases and tilesets
+    // draw font_renderer::TOFU as an empty box, the glyph cache only has it if the font itself
+    // does and leaves the cell blank otherwise.
+    pub fn replacement_glyph(&mut self, replacement: char) -> &mut Self {
+        self.replacement_glyph = Some(replacement);
+        self
+    }
+
+    // Fonts are looked up in and added to the registry instead of loaded for this console alone
+    pub fn fonts(&mut self, fonts: &FontRegistry) -> &mut Self {
+        self.fonts = Some(fonts.clone());
+        self
+    }
+
+    // How a tileset given to font is cut up, 16x16 CP437 by default
+    pub fn tileset(&mut self, layout: TilesetLayout, codepage: &[char]) -> &mut Self {
+        self.tileset_layout = layout;
+        self.codepage = codepage.to_vec();
         self
     }
 
-    pub fn top_align(&mut self) -> &mut Self {
-        self.offset = (self.offset.0, (1.0 - self.scale.1) * 2.0);
+    // Characters rasterized up front, others are added as they're drawn when the glyph cache is on
+    pub fn charset(&mut self, charset: Charset) -> &mut Self {
+        self.charset = charset;
         self
     }
 
-    pub fn left_align(&mut self) -> &mut Self {
-        self.offset = ((1.0 - self.scale.0) * 2.0, self.offset.1);
+    // Without the cache only the charset can be drawn, baked into one texture
+    pub fn glyph_cache(&mut self, glyph_cache: bool) -> &mut Self {
+        self.glyph_cache = glyph_cache;
         self
     }
 
-    pub fn right_align(&mut self) -> &mut Self {
-        self.offset = ((1.0 + 3.0 * self.scale.0), self.offset.1);
+    // Renders from a distance field so text stays sharp however far the console is scaled.
+    // Takes the place of the glyph cache, only the charset can be drawn.
+    pub fn sdf(&mut self, sdf: bool) -> &mut Self {
+        self.sdf = sdf;
         self
     }
 
+    // Lays the console out inside another one instead of the whole screen. Scale is then a
+    // fraction of that console's size.
     pub fn relative_to(&mut self, console: &Console) -> &mut Self {
-        self.relative = Some(
-            RelativeConsole {
-                scale: console.screen_scaling,
-                offset: console.screen_offset,
-            }
-        );
+        self.parent = Some(console.region());
         self
     }
 
     pub fn font_from(&mut self, console: &Console) -> &mut Self {
-        self.font_info = Some(console.font_info.clone());
+        self.font_info = console.backend.font_info().cloned();
         self
     }
 
-    pub fn build(&self, res: &Resources, gl: &gl::Gl) -> Result<Console, failure::Error> {
-        // Left bias the offset
-        let offset = if self.centered {
-            self.offset // (self.offset.1 + self.scale.0 / 2.0, self.offset.1 + self.scale.0 / 2.0)
+    // Shaders and fonts come from assets, so consoles built from the same one share them
+    pub fn build(&self, assets: &Assets) -> Result<Console, failure::Error> {
+        let fonts = self.fonts.as_ref().unwrap_or_else(|| assets.fonts());
+        let font_info = match &self.font_info {
+            Some(font_info) => font_info.clone(),
+            None => fonts.load(assets.resources(), assets.gl(), self.font_key())?,
+        };
+        let program = if font_info.is_sdf() {
+            assets.program_from_files("shaders/glyph_sdf", &["shaders/glyph.vert", "shaders/glyph_sdf.frag"])?
         } else {
-            (self.offset.0 - (1.0 - self.scale.0), self.offset.1 - (1.0 - self.scale.1))
+            assets.program("shaders/glyph")?
         };
-        match &self.relative {
-            None => Console::new(res, gl, self.size, self.scale, offset, self.background, self.layer, self.font_info.clone()),
-            Some(relative) => {
-                let offset = (offset.0 + relative.offset.0, offset.1 + relative.offset.1);
-                let scale = (self.scale.0 * relative.scale.0, self.scale.1 * relative.scale.1);
-                Console::new(res, gl, self.size, scale, offset, self.background, self.layer, self.font_info.clone())
-            }
-        }
+        let backend = GlyphRenderer::new(assets.gl(), program, font_info);
+        Ok(self.build_with_backend(Box::new(backend)))
     }
-}
 
-impl Console {
-    fn new(res: &Resources,
-               gl: &gl::Gl,
-               map_size: (u32, u32),
-               screen_scaling: (f32, f32),
-               screen_offset: (f32, f32),
-               background: Color,
-               height: u32,
-               font: Option<FontInfo>) -> Result<Self, failure::Error> {
-        let shader_program = render_gl::Program::from_res(
-            &gl, &res, "shaders/glyph",
-        )?;
-
-        let font_info = match font {
-            Some(font) => font,
-            None => {
-                let font_bytes = res.load_bytes_from_file("ubuntu-mono-regular.ttf").unwrap();
-                let (font_img, glyph_map) = load_bitmap(font_bytes);
-                font_img.save("321.png");
-                let texture_scale_u32 = font_img.dimensions();
-                let texture = Texture::from_img(gl, font_img, gl::RGBA)?;
-                let texture_scale = (texture_scale_u32.0 as i32, texture_scale_u32.1 as i32);
-                FontInfo {
-                    glyph_map,
-                    texture_scale,
-                    texture,
-                }
-            }
-        };
+    // A console that keeps its cells but never draws, for use without a window
+    pub fn build_headless(&self) -> Console {
+        self.build_with_backend(Box::new(HeadlessBackend))
+    }
 
-        let vao = VertexArray::new(&gl);
-        let vbo = ArrayBuffer::new(&gl);
-        let ebo = ElementArrayBuffer::new(&gl);
-
-        Ok(Console {
-            is_dirty: RefCell::new(Dirty(true)),
-            num_vert: RefCell::new(Num(0)),
-            vao,
-            vbo,
-            ebo,
-            font_info,
-            height,
-            glyphs: HashMap::new(),
-            program: shader_program,
-            dimensions: map_size,
-            screen_scaling,
-            screen_offset,
-            default_background: background,
-            scale_modifier: (1.0, 1.0)
-        })
+    pub fn build_with_backend(&self, mut backend: Box<dyn RenderBackend>) -> Console {
+        let layout = Layout::new(self.anchor, self.scale).margin(self.margin);
+        let (screen_scaling, screen_offset) = layout.resolve(self.parent.unwrap_or_else(Region::screen), self.size, (1.0, 1.0));
+        if let (Some(replacement), Some(font_info)) = (self.replacement_glyph, backend.font_info_mut()) {
+            font_info.set_replacement(replacement);
+        }
+        Console {
+            cells: CellBuffer::with_layers(self.size, self.layer_count, self.background),
+            retained: None,
+            resize_hook: None,
+            backend,
+            placement: Placement {
+                screen_scaling,
+                screen_offset,
+                scale_modifier: (1.0, 1.0),
+                height: self.layer,
+            },
+            layout,
+            parent: self.parent,
+            root: Region::screen(),
+            visible: true,
+            pixel_grid: None,
+        }
     }
 
-    pub fn fill_background(&mut self, background: Color) {
-        for x in 0..self.dimensions.0 {
-            for y in 0..self.dimensions.1 {
-                self.put_char(' ', x as i32, y as i32, background, Some(background), 1);
-            }
+    fn font_key(&self) -> FontKey {
+        FontKey {
+            name: self.font.clone(),
+            charset: self.charset.clone(),
+            glyph_cache: self.glyph_cache,
+            sdf: self.sdf,
+            tileset_layout: self.tileset_layout,
+            codepage: self.codepage.clone(),
         }
     }
+}
+
+impl Console {
+    pub fn fill_background(&mut self, background: Color) {
+        self.cells.fill_background(background);
+    }
 
     pub fn clear(&mut self) {
-        self.glyphs.clear();
-        self.is_dirty.borrow_mut().set(true);
+        self.retained = None;
+        self.cells.clear();
     }
 
-    pub fn put_text(&mut self, text: &String, x: i32, y: i32,foreground: data::f32_f32_f32_f32, background: Option<data::f32_f32_f32_f32>, layer: u32) {
-        for (index, c) in text.chars().enumerate() {
-            self.put_char(c, x + index as i32, y, foreground, background, layer);
-        }
+    pub fn put_text(&mut self, text: &str, x: i32, y: i32, foreground: data::f32_f32_f32_f32, background: Option<data::f32_f32_f32_f32>, layer: u32) {
+        self.cells.put_text(text, x, y, foreground, background, layer);
     }
 
     pub fn put_char(&mut self, c: char, x: i32, y: i32, foreground: data::f32_f32_f32_f32, background: Option<data::f32_f32_f32_f32>, layer: u32) {
-        if x < 0 || y < 0 {
-            return
+        self.cells.put_char(c, x, y, foreground, background, layer);
+    }
+
+    pub fn put_text_styled(&mut self, text: &str, x: i32, y: i32, foreground: Color, background: Option<Color>, style: Style, layer: u32) {
+        self.cells.put_text_styled(text, x, y, foreground, background, style, layer);
+    }
+
+    pub fn put_spans(&mut self, spans: &[Span], x: i32, y: i32, foreground: Color, background: Option<Color>, layer: u32) -> u32 {
+        self.cells.put_spans(spans, x, y, foreground, background, layer)
+    }
+
+    // See parse_markup for the syntax
+    pub fn put_markup(&mut self, markup: &str, x: i32, y: i32, foreground: Color, background: Option<Color>, palette: &Palette, layer: u32) -> u32 {
+        self.cells.put_spans(&parse_markup(markup, palette), x, y, foreground, background, layer)
+    }
+
+    pub fn print_rect(&mut self, rect: Rect, text: &str, align: Align, wrap: bool, foreground: Color, background: Option<Color>, layer: u32) -> u32 {
+        self.cells.print_rect(rect, text, align, wrap, foreground, background, layer)
+    }
+
+    pub fn print_rect_spans(&mut self, rect: Rect, spans: &[Span], align: Align, wrap: bool, foreground: Color, background: Option<Color>, layer: u32) -> u32 {
+        self.cells.print_rect_spans(rect, spans, align, wrap, foreground, background, layer)
+    }
+
+    pub fn hline(&mut self, x: i32, y: i32, length: u32, c: char, foreground: Color, background: Option<Color>, layer: u32) {
+        self.cells.hline(x, y, length, c, foreground, background, layer);
+    }
+
+    pub fn vline(&mut self, x: i32, y: i32, length: u32, c: char, foreground: Color, background: Option<Color>, layer: u32) {
+        self.cells.vline(x, y, length, c, foreground, background, layer);
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, c: char, foreground: Color, background: Option<Color>, layer: u32) {
+        self.cells.fill_rect(rect, c, foreground, background, layer);
+    }
+
+    pub fn draw_frame(&mut self, rect: Rect, style: FrameStyle, title: Option<&str>, foreground: Color, background: Option<Color>, layer: u32) {
+        self.cells.draw_frame(rect, style, title, foreground, background, layer);
+    }
+
+    pub fn draw_progress_bar(&mut self, rect: Rect, fraction: f32, filled: Color, empty: Color, layer: u32) {
+        self.cells.draw_progress_bar(rect, fraction, filled, empty, layer);
+    }
+
+    pub fn put_char_styled(&mut self, c: char, x: i32, y: i32, foreground: Color, background: Option<Color>, style: Style, layer: u32) {
+        self.cells.put_char_styled(c, x, y, foreground, background, style, layer);
+    }
+
+    // The cell under a window position, in pixels from the window's top left corner like
+    // glutin's cursor events, or None when the console isn't there
+    pub fn cell_at(&self, window_pos: (f32, f32), window_size: (f32, f32)) -> Option<(u32, u32)> {
+        if !self.visible {
+            return None;
         }
-        let background = match background {
-            Some(b) => b,
-            None => self.default_background
-        };
-        self.is_dirty.borrow_mut().set(true);
-        self.glyphs.insert((self.coordinates_to_index(x as u32, y as u32), layer), Glyph::new(c, background, foreground));
-    }
-
-    fn glyph_size(&self) -> (f32, f32) {
-        (2.0 / self.dimensions.0 as f32 * self.screen_scaling.0 * self.scale_modifier.0, 2.0 / self.dimensions.1 as f32 * self.screen_scaling.1 * self.scale_modifier.1)
-    }
-
-    fn coordinates_to_index(&self, x: u32, y: u32) -> u32 {
-        x + y * self.dimensions.0
-    }
-
-    fn index_to_coordinates(&self, index: u32) -> (u32, u32) {
-        (index % self.dimensions.0, index / self.dimensions.0)
-    }
-
-    fn coordinates_to_fractional(&self, coordinates: (u32, u32)) -> (f32, f32) {
-        (((coordinates.0 as f32 / self.dimensions.0 as f32) * 2.0 - 1.0)  * self.screen_scaling.0 * self.scale_modifier.0 + self.screen_offset.0,
-         ((coordinates.1 as f32 / self.dimensions.1 as f32) * 2.0 - 1.0) * self.screen_scaling.1 * self.scale_modifier.1 + self.screen_offset.1 )
-    }
-
-    fn load_gl(&self, gl: &gl::Gl) -> i32 {
-        let start = std::time::Instant::now();
-        let mut vertices: Vec<Vertex> = vec![];
-        let mut indices: Vec<gl::types::GLuint> = vec![];
-        let mut num_glyphs = 0;
-        for (index, glyph) in self.glyphs.iter() {
-            let bounding_box = self.font_info.glyph_map.get(&glyph.character).unwrap();
-            let scaled_bounding_box = self.glyph_size();
-            let (index, layer) = *index;
-            let layer = layer as f32 / 255.0 * -1.0 * self.height as f32;
-            let coordinates = self.coordinates_to_fractional(self.index_to_coordinates(index));
-            let index_offset = vertices.len() as u32;
-
-            vertices.append(&mut vec![
-                Vertex { position: (scaled_bounding_box.0 + coordinates.0, scaled_bounding_box.1 + coordinates.1, layer).into(),
-                    texture: bounding_box.top_right(self.font_info.texture_scale).into(),
-                    foreground: glyph.foreground,
-                    background: glyph.background},
-                Vertex { position: (scaled_bounding_box.0 + coordinates.0, coordinates.1, layer ).into(),
-                    texture: bounding_box.bottom_right(self.font_info.texture_scale).into(),
-                    foreground: glyph.foreground,
-                    background: glyph.background },
-                Vertex { position: (coordinates.0, coordinates.1, layer).into(),
-                    texture: bounding_box.bottom_left(self.font_info.texture_scale).into(),
-                    foreground: glyph.foreground,
-                    background: glyph.background },
-                Vertex { position: (coordinates.0, scaled_bounding_box.1 + coordinates.1, layer).into(),
-                    texture: bounding_box.top_left(self.font_info.texture_scale).into(),
-                    foreground: glyph.foreground,
-                    background: glyph.background },
-            ]);
-            indices.append(&mut vec![
-                index_offset, 1 + index_offset, 3 + index_offset, 1 + index_offset, 2 + index_offset, 3 + index_offset,
-            ]);
-            num_glyphs += 1;
+        let fractional = (window_pos.0 / window_size.0 * 2.0 - 1.0, 1.0 - window_pos.1 / window_size.1 * 2.0);
+        self.placement.fractional_to_coordinates(self.dimensions(), fractional)
+    }
+
+    pub fn layer(&self) -> u32 {
+        self.placement.height
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    // Hidden consoles keep their cells but render does nothing
+    pub fn set_visible(&mut self, visible: bool) {
+        if visible && !self.visible {
+            self.cells.set_dirty();
         }
-        if num_glyphs > 0 {
-            self.vao.bind();
+        self.visible = visible;
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+        self.resolve_layout();
+    }
 
-            self.vbo.bind();
-            self.vbo.dynamic_draw_data(&vertices);
+    // Places the console again inside parent, for when the parent moved or was resized
+    pub fn layout_in(&mut self, parent: Region) {
+        self.parent = Some(parent);
+        self.resolve_layout();
+    }
 
-            self.ebo.bind();
-            self.ebo.dynamic_draw_data(&indices);
+    // The part of the screen the console covers, to lay other consoles out in
+    pub fn region(&self) -> Region {
+        let placement = &self.placement;
+        Region {
+            center: placement.screen_offset,
+            half_size: (placement.screen_scaling.0 * placement.scale_modifier.0,
+                        placement.screen_scaling.1 * placement.scale_modifier.1),
+        }
+    }
 
-            Vertex::vertex_attrib_pointers(&gl);
+    fn parent_region(&self) -> Region {
+        self.parent.unwrap_or(self.root)
+    }
 
-            self.vbo.unbind();
-            self.vao.unbind();
-            self.ebo.unbind();
+    fn resolve_layout(&mut self) {
+        let (screen_scaling, mut screen_offset) = self.layout.resolve(self.parent_region(), self.dimensions(), self.placement.scale_modifier);
+        if let Some(window_size) = self.pixel_grid {
+            let modifier = self.placement.scale_modifier;
+            let snap = |offset: f32, half_size: f32, pixels: f32| {
+                let corner = ((offset - half_size + 1.0) / 2.0 * pixels).round();
+                corner / pixels * 2.0 - 1.0 + half_size
+            };
+            screen_offset = (snap(screen_offset.0, screen_scaling.0 * modifier.0, window_size.0),
+                             snap(screen_offset.1, screen_scaling.1 * modifier.1, window_size.1));
         }
-        num_glyphs
+        self.placement.screen_scaling = screen_scaling;
+        self.placement.screen_offset = screen_offset;
+        self.cells.set_dirty();
+    }
+
+    // Pixels the layout gives the console before any transformer shrinks it
+    fn available_pixels(&self, window_size: (f32, f32)) -> (f32, f32) {
+        let (screen_scaling, _) = self.layout.resolve(self.parent_region(), self.dimensions(), (1.0, 1.0));
+        (screen_scaling.0 * window_size.0, screen_scaling.1 * window_size.1)
     }
 
     fn set_dirty(&mut self) {
-        self.is_dirty.borrow_mut().set(true);
+        self.cells.set_dirty();
     }
 
     pub fn dimensions(&self) -> (u32, u32) {
-        self.dimensions
+        self.cells.dimensions()
+    }
+
+    // Changes how many cells the console has, every layer the same way. It keeps its place on
+    // screen, so cells get bigger or smaller unless a transformer makes up for it.
+    pub fn resize(&mut self, dimensions: (u32, u32), policy: ResizePolicy) {
+        let old_dimensions = self.dimensions();
+        if dimensions == old_dimensions {
+            return;
+        }
+        match policy {
+            ResizePolicy::Preserve => self.resize_preserving(dimensions),
+            ResizePolicy::Clip => {
+                self.retained = None;
+                self.cells.resize(dimensions);
+            },
+            ResizePolicy::Reanchor(anchor) => {
+                self.retained = None;
+                let direction = anchor.direction();
+                let shift = |old: u32, new: u32, direction: f32| {
+                    ((new as i32 - old as i32) as f32 * (direction + 1.0) / 2.0) as i32
+                };
+                let offset = (shift(old_dimensions.0, dimensions.0, direction.0),
+                              shift(old_dimensions.1, dimensions.1, direction.1));
+                self.cells.resize_shifted(dimensions, offset);
+            },
+        }
+        self.resolve_layout();
+        if let Some(mut hook) = self.resize_hook.take() {
+            hook(self, old_dimensions);
+            // The hook may have replaced itself
+            if self.resize_hook.is_none() {
+                self.resize_hook = Some(hook);
+            }
+        }
+    }
+
+    fn resize_preserving(&mut self, dimensions: (u32, u32)) {
+        let current = self.cells.dimensions();
+        let mut retained = self.retained.take().unwrap_or_else(|| {
+            CellBuffer::with_layers(current, self.cells.layer_count(), self.cells.default_background())
+        });
+        let kept = retained.dimensions();
+        retained.resize((kept.0.max(current.0), kept.1.max(current.1)));
+        retained.copy_from(&self.cells);
+        self.cells.resize(dimensions);
+        self.cells.copy_from(&retained);
+        self.retained = Some(retained);
     }
 
-    pub fn render(&self, gl: &gl::Gl) {
-        let start = std::time::Instant::now();
-        unsafe {
-            gl.Enable(gl::DEPTH_TEST);
+    pub fn on_resize<F>(&mut self, hook: F) where F: FnMut(&mut Console, (u32, u32)) + 'static {
+        self.resize_hook = Some(Box::new(hook));
+    }
+
+    pub fn get_cell(&self, x: u32, y: u32, layer: u32) -> Option<&Glyph> {
+        self.cells.get_cell(x, y, layer)
+    }
+
+    pub fn topmost_cell(&self, x: u32, y: u32) -> Option<(u32, &Glyph)> {
+        self.cells.topmost_cell(x, y)
+    }
+
+    pub fn row_text(&self, y: u32) -> String {
+        self.cells.row_text(y)
+    }
+
+    pub fn rect_text(&self, rect: Rect) -> String {
+        self.cells.rect_text(rect)
+    }
+
+    // What's on screen with the layers flattened the way the renderers draw them
+    pub fn to_plain_text(&self) -> String {
+        export::to_plain_text(&self.cells)
+    }
+
+    pub fn to_ansi(&self) -> String {
+        export::to_ansi(&self.cells)
+    }
+
+    pub fn to_html(&self) -> String {
+        export::to_html(&self.cells)
+    }
+
+    // Draws a REXPaint image from the resources with its bottom left corner at position,
+    // .xp layer n going to console layer n. Layers the console doesn't have are left out.
+    pub fn load_xp(&mut self, res: &Resources, resource_name: &str, position: (i32, i32)) -> Result<(), failure::Error> {
+        let image = res.load_xp(resource_name)?;
+        blit(&image, Rect::with_dimensions(image.dimensions()), &mut self.cells, position, 1.0, 1.0);
+        Ok(())
+    }
+
+    // Every layer as a gzip'd REXPaint image, see write_xp for what doesn't survive the trip.
+    // Characters outside CP437 come out as '?'.
+    pub fn save_xp<W: std::io::Write>(&self, writer: W) -> Result<(), XpError> {
+        write_xp(&self.cells, writer)
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        self.cells.snapshot()
+    }
+
+    // Consoles without a font, like headless ones, accept anything
+    pub fn can_render(&self, c: char) -> bool {
+        self.backend.font_info().map_or(true, |font_info| font_info.can_render(c))
+    }
+
+    pub fn set_replacement_glyph(&mut self, replacement: char) {
+        if let Some(font_info) = self.backend.font_info_mut() {
+            font_info.set_replacement(replacement);
+            self.cells.set_dirty();
         }
-        if self.is_dirty.borrow().0 || true {
-            let num_glyphs = self.load_gl(&gl);
-            self.is_dirty.borrow_mut().set(false);
-            self.num_vert.borrow_mut().set(num_glyphs);
+    }
+
+    pub fn on_missing_glyph<F>(&self, hook: F) where F: FnMut(char, char) + 'static {
+        if let Some(font_info) = self.backend.font_info() {
+            font_info.on_missing_glyph(Box::new(hook));
         }
-        self.program.set_used();
-
-        self.font_info.texture.bind();
-
-        self.vao.bind();
-        unsafe {
-            gl.DrawElements(
-                gl::TRIANGLES,
-                self.num_vert.borrow().0 * 6,
-                gl::UNSIGNED_INT,
-                ptr::null(),
-            );
+    }
+
+    pub fn cells(&self) -> &CellBuffer {
+        &self.cells
+    }
+
+    pub fn cells_mut(&mut self) -> &mut CellBuffer {
+        &mut self.cells
+    }
+
+    pub fn render(&mut self) {
+        if !self.visible {
+            return;
         }
+        self.backend.render(&self.cells, &self.placement);
+        self.cells.clear_dirty();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // "top" on the top row and "bot" on the bottom one
+    fn console() -> Console {
+        let mut console = ConsoleBuilder::with_dimensions((4, 3)).build_headless();
+        console.put_text("top", 0, 2, *colors::WHITE, None, 1);
+        console.put_text("bot", 0, 0, *colors::WHITE, None, 2);
+        console
+    }
+
+    #[test]
+    fn clip_drops_what_no_longer_fits() {
+        let mut console = console();
+        console.resize((2, 2), ResizePolicy::Clip);
+        assert_eq!(console.snapshot().to_string(), "  \nbo");
+        console.resize((4, 3), ResizePolicy::Clip);
+        assert_eq!(console.snapshot().to_string(), "    \n    \nbo  ");
+    }
+
+    #[test]
+    fn preserve_brings_cells_back_when_growing() {
+        let mut console = console();
+        console.resize((2, 2), ResizePolicy::Preserve);
+        assert_eq!(console.snapshot().to_string(), "  \nbo");
+        console.put_char('x', 1, 1, *colors::WHITE, None, 1);
+        console.resize((4, 3), ResizePolicy::Preserve);
+        assert_eq!(console.snapshot().to_string(), "top \n x  \nbot ");
+    }
+
+    #[test]
+    fn preserve_forgets_cleared_cells() {
+        let mut console = console();
+        console.resize((2, 2), ResizePolicy::Preserve);
+        console.clear();
+        console.resize((4, 3), ResizePolicy::Preserve);
+        assert_eq!(console.snapshot().to_string(), "    \n    \n    ");
+    }
+
+    #[test]
+    fn reanchor_keeps_content_at_the_anchor() {
+        let mut console = console();
+        console.resize((3, 2), ResizePolicy::Reanchor(Anchor::TopLeft));
+        assert_eq!(console.snapshot().to_string(), "top\n   ");
+
+        let mut console = self::console();
+        console.resize((6, 5), ResizePolicy::Reanchor(Anchor::Center));
+        assert_eq!(console.snapshot().to_string(), "      \n top  \n      \n bot  \n      ");
+
+        let mut console = self::console();
+        console.resize((2, 3), ResizePolicy::Reanchor(Anchor::BottomRight));
+        assert_eq!(console.snapshot().to_string(), "p \n  \nt ");
+    }
+
+    #[test]
+    fn every_layer_is_resized() {
+        let mut console = console();
+        console.resize((5, 4), ResizePolicy::Reanchor(Anchor::TopLeft));
+        assert_eq!(console.get_cell(0, 3, 1).map(|glyph| glyph.character), Some('t'));
+        assert_eq!(console.get_cell(0, 1, 2).map(|glyph| glyph.character), Some('b'));
+    }
+
+    #[test]
+    fn the_hook_sees_the_new_size() {
+        let mut console = console();
+        let seen = Rc::new(RefCell::new(vec![]));
+        let log = Rc::clone(&seen);
+        console.on_resize(move |console, old| log.borrow_mut().push((old, console.dimensions())));
+        console.resize((2, 2), ResizePolicy::Clip);
+        console.resize((2, 2), ResizePolicy::Clip);
+        console.resize((5, 1), ResizePolicy::Preserve);
+        assert_eq!(*seen.borrow(), vec![((4, 3), (2, 2)), ((2, 2), (5, 1))]);
+    }
+
+    // A backend whose font has cells of a fixed size, for the transformers that look at it
+    struct FixedCells((f32, f32));
+
+    impl RenderBackend for FixedCells {
+        fn render(&mut self, _cells: &CellBuffer, _placement: &Placement) {}
+
+        fn cell_size(&self) -> Option<(f32, f32)> {
+            Some(self.0)
+        }
+    }
+
+    fn assert_region(console: &Console, center: (f32, f32), half_size: (f32, f32)) {
+        let region = console.region();
+        let close = |a: (f32, f32), b: (f32, f32)| (a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5;
+        assert!(close(region.center, center) && close(region.half_size, half_size),
+                "{:?} isn't centered at {:?} with half size {:?}", region, center, half_size);
+    }
+
+    #[test]
+    fn fit_cells_adds_cells_as_the_window_grows() {
+        let mut console = ConsoleBuilder::with_dimensions((4, 3)).build_headless();
+        Transformer::FitCells((10.0, 10.0)).apply(&mut console, (100.0, 80.0));
+        assert_eq!(console.dimensions(), (10, 8));
+        assert_region(&console, (0.0, 0.0), (1.0, 1.0));
+
+        // Leftover pixels are left empty on the side away from the anchor
+        Transformer::FitCells((10.0, 10.0)).apply(&mut console, (105.0, 85.0));
+        assert_eq!(console.dimensions(), (10, 8));
+        assert_region(&console, (-1.0 + 100.0 / 105.0, -1.0 + 80.0 / 85.0), (100.0 / 105.0, 80.0 / 85.0));
+    }
+
+    #[test]
+    fn integer_scale_uses_whole_multiples_of_the_cell_size() {
+        let mut console = ConsoleBuilder::with_dimensions((4, 3)).build_with_backend(Box::new(FixedCells((8.0, 8.0))));
+        // 32x24 pixels fit three times
+        Transformer::IntegerScale.apply(&mut console, (100.0, 100.0));
+        assert_region(&console, (-0.04, -0.28), (0.96, 0.72));
+        // and only at half size into a smaller window
+        Transformer::IntegerScale.apply(&mut console, (20.0, 20.0));
+        assert_region(&console, (-0.2, -0.4), (0.8, 0.6));
+        assert_eq!(console.dimensions(), (4, 3));
+    }
+
+    #[test]
+    fn letterbox_centers_the_ratio_in_the_window() {
+        let mut console = ConsoleBuilder::with_dimensions((4, 3)).build_headless();
+        Transformer::Letterbox(2.0, *colors::BLACK).apply(&mut console, (400.0, 100.0));
+        assert_region(&console, (0.0, 0.0), (0.5, 1.0));
+        Transformer::Letterbox(2.0, *colors::BLACK).apply(&mut console, (200.0, 200.0));
+        assert_region(&console, (0.0, 0.0), (1.0, 0.5));
+        Transformer::Letterbox(2.0, *colors::BLACK).apply(&mut console, (200.0, 100.0));
+        assert_region(&console, (0.0, 0.0), (1.0, 1.0));
+    }
+
+    #[test]
+    fn letterbox_keeps_consoles_inside_their_parent() {
+        let parent = ConsoleBuilder::with_dimensions((4, 4)).scale((0.5, 0.5)).build_headless();
+        let mut console = ConsoleBuilder::with_dimensions((2, 2)).relative_to(&parent).build_headless();
+        Transformer::Letterbox(2.0, *colors::BLACK).apply(&mut console, (400.0, 100.0));
+        assert_region(&console, (-0.5, -0.5), (0.5, 0.5));
     }
 }
@@ -0,0 +1,241 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use crate::console::{Console, Transformer};
+use crate::Color;
+
+pub type ConsoleHandle = Rc<RefCell<Console>>;
+
+struct Entry {
+    console: Weak<RefCell<Console>>,
+    parent: Option<Weak<RefCell<Console>>>,
+}
+
+// Keeps track of every console on screen, lays them out again when the window changes and
+// draws them in layer order. Consoles are held weakly, dropping the last handle to one
+// takes it off screen.
+pub struct ConsoleRoot {
+    entries: Vec<Entry>,
+    transformers: Vec<Transformer>,
+    window_size: (f32, f32),
+}
+
+impl ConsoleRoot {
+    pub fn new(window_size: (f32, f32)) -> Self {
+        ConsoleRoot {
+            entries: Vec::new(),
+            transformers: Vec::new(),
+            window_size,
+        }
+    }
+
+    // Applied in the order added to every console that isn't laid out inside another one
+    pub fn add_transformer(&mut self, transformer: Transformer) {
+        self.transformers.push(transformer);
+        self.relayout();
+    }
+
+    // What to clear the window with so letterboxing bars show up in their color
+    pub fn bar_color(&self) -> Option<Color> {
+        self.transformers.iter().rev()
+            .find_map(|transformer| match transformer {
+                Transformer::Letterbox(_, color) => Some(*color),
+                _ => None,
+            })
+    }
+
+    pub fn add(&mut self, console: Console) -> ConsoleHandle {
+        self.register(console, None)
+    }
+
+    // Lays console out inside parent, it follows the parent around instead of being
+    // transformed on its own
+    pub fn add_child(&mut self, parent: &ConsoleHandle, console: Console) -> ConsoleHandle {
+        self.register(console, Some(Rc::downgrade(parent)))
+    }
+
+    fn register(&mut self, console: Console, parent: Option<Weak<RefCell<Console>>>) -> ConsoleHandle {
+        let handle = Rc::new(RefCell::new(console));
+        let entry = Entry {
+            console: Rc::downgrade(&handle),
+            parent,
+        };
+        self.place(&entry);
+        self.entries.push(entry);
+        handle
+    }
+
+    // Minimized windows report a size of 0, consoles keep their last layout until it comes back
+    pub fn resize(&mut self, window_size: (f32, f32)) {
+        if window_size.0 <= 0.0 || window_size.1 <= 0.0 {
+            return;
+        }
+        self.window_size = window_size;
+        self.relayout();
+    }
+
+    pub fn window_size(&self) -> (f32, f32) {
+        self.window_size
+    }
+
+    // Consoles still alive
+    pub fn len(&self) -> usize {
+        self.entries.iter().filter(|entry| entry.console.upgrade().is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Parents are always registered before their children, so going in order places every
+    // parent before anything inside it
+    fn relayout(&mut self) {
+        self.entries.retain(|entry| entry.console.upgrade().is_some());
+        for entry in &self.entries {
+            self.place(entry);
+        }
+    }
+
+    fn place(&self, entry: &Entry) {
+        let console = match entry.console.upgrade() {
+            Some(console) => console,
+            None => return,
+        };
+        let mut console = console.borrow_mut();
+        match entry.parent.as_ref().and_then(|parent| parent.upgrade()) {
+            Some(parent) => console.layout_in(parent.borrow().region()),
+            None => {
+                for transformer in &self.transformers {
+                    transformer.apply(&mut console, self.window_size);
+                }
+            }
+        }
+    }
+
+    // The topmost console under a window position and the cell it has there
+    pub fn cell_at(&self, window_pos: (f32, f32)) -> Option<(ConsoleHandle, (u32, u32))> {
+        self.entries.iter()
+            .filter_map(|entry| entry.console.upgrade())
+            .filter_map(|console| {
+                let cell = console.borrow().cell_at(window_pos, self.window_size)?;
+                Some((console, cell))
+            })
+            .max_by_key(|(console, _)| console.borrow().layer())
+    }
+
+    // Lowest layer first
+    pub fn render(&mut self) {
+        self.entries.retain(|entry| entry.console.upgrade().is_some());
+        let mut consoles: Vec<ConsoleHandle> = self.entries.iter()
+            .filter_map(|entry| entry.console.upgrade())
+            .collect();
+        consoles.sort_by_key(|console| console.borrow().layer());
+        for console in consoles {
+            console.borrow_mut().render();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Placement, RenderBackend};
+    use crate::cell_buffer::CellBuffer;
+    use crate::color::colors;
+    use crate::console::ConsoleBuilder;
+    use crate::layout::Anchor;
+
+    // Writes its console's layer to the log whenever it's drawn
+    struct Recorder(Rc<RefCell<Vec<u32>>>);
+
+    impl RenderBackend for Recorder {
+        fn render(&mut self, _cells: &CellBuffer, placement: &Placement) {
+            self.0.borrow_mut().push(placement.height);
+        }
+    }
+
+    fn console(layer: u32) -> Console {
+        ConsoleBuilder::with_dimensions((4, 4)).layer(layer).build_headless()
+    }
+
+    #[test]
+    fn consoles_render_lowest_layer_first() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut root = ConsoleRoot::new((100.0, 100.0));
+        let _handles: Vec<ConsoleHandle> = [3, 1, 2].iter()
+            .map(|&layer| {
+                let console = ConsoleBuilder::with_dimensions((4, 4)).layer(layer).build_with_backend(Box::new(Recorder(Rc::clone(&log))));
+                root.add(console)
+            })
+            .collect();
+        root.render();
+        assert_eq!(*log.borrow(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn dropped_handles_leave_the_root() {
+        let mut root = ConsoleRoot::new((100.0, 100.0));
+        let kept = root.add(console(1));
+        let dropped = root.add(console(2));
+        assert_eq!(root.len(), 2);
+        drop(dropped);
+        assert_eq!(root.len(), 1);
+        root.render();
+        assert_eq!(root.entries.len(), 1);
+        drop(kept);
+        assert!(root.is_empty());
+    }
+
+    #[test]
+    fn children_follow_their_parent_on_relayout() {
+        let mut root = ConsoleRoot::new((200.0, 100.0));
+        root.add_transformer(Transformer::Letterbox(1.0, *colors::BLACK));
+        let parent = root.add(console(1));
+        let child = ConsoleBuilder::with_dimensions((2, 2)).anchor(Anchor::Center).scale((0.5, 0.5)).build_headless();
+        let child = root.add_child(&parent, child);
+        assert_eq!(parent.borrow().region().half_size, (0.5, 1.0));
+        assert_eq!(child.borrow().region().half_size, (0.25, 0.5));
+
+        root.resize((100.0, 200.0));
+        assert_eq!(parent.borrow().region().half_size, (1.0, 0.5));
+        assert_eq!(child.borrow().region().half_size, (0.5, 0.25));
+    }
+
+    #[test]
+    fn cell_at_picks_the_topmost_console() {
+        let mut root = ConsoleRoot::new((100.0, 100.0));
+        let _low = root.add(console(1));
+        let high = root.add(ConsoleBuilder::with_dimensions((2, 2)).layer(5).scale((0.5, 0.5)).build_headless());
+        let _middle = root.add(console(3));
+
+        let (console, cell) = root.cell_at((10.0, 90.0)).unwrap();
+        assert!(Rc::ptr_eq(&console, &high));
+        assert_eq!(cell, (0, 0));
+        let (console, cell) = root.cell_at((90.0, 10.0)).unwrap();
+        assert_eq!((console.borrow().layer(), cell), (3, (3, 3)));
+        assert!(root.cell_at((100.5, 50.0)).is_none());
+    }
+
+    #[test]
+    fn the_last_letterbox_gives_the_bar_color() {
+        let mut root = ConsoleRoot::new((100.0, 100.0));
+        assert_eq!(root.bar_color(), None);
+        root.add_transformer(Transformer::Letterbox(1.0, *colors::DARK_RED));
+        root.add_transformer(Transformer::Letterbox(2.0, *colors::LIGHT_SLATE_BLUE));
+        root.add_transformer(Transformer::IntegerScale);
+        assert_eq!(root.bar_color(), Some(*colors::LIGHT_SLATE_BLUE));
+    }
+
+    #[test]
+    fn zero_sized_windows_keep_the_last_layout() {
+        let mut root = ConsoleRoot::new((100.0, 80.0));
+        root.add_transformer(Transformer::FitCells((10.0, 10.0)));
+        let console = root.add(ConsoleBuilder::with_dimensions((4, 3)).build_headless());
+        let region = console.borrow().region();
+
+        root.resize((0.0, 0.0));
+        root.resize((0.0, 80.0));
+        assert_eq!(root.window_size(), (100.0, 80.0));
+        assert_eq!(console.borrow().dimensions(), (10, 8));
+        assert_eq!(console.borrow().region(), region);
+    }
+}
@@ -1,15 +1,44 @@
 use crate::render_gl::data;
 
+// Corner of the unit quad shared by every glyph
 #[derive(VertexAttribPointers)]
 #[derive(Copy, Clone, Debug)]
 #[repr(C, packed)]
-pub struct Vertex {
+pub struct QuadVertex {
     #[location = 0]
-    pub position: data::f32_f32_f32,
+    pub corner: data::f32_f32,
+}
+
+#[derive(VertexAttribPointers)]
+#[derive(Copy, Clone, Debug)]
+#[repr(C, packed)]
+#[divisor = 1]
+pub struct GlyphInstance {
+    // cell index, layer and style bits
     #[location = 1]
-    pub texture: data::f32_f32,
+    pub cell: data::f32_f32_f32,
+    // bottom left and top right of the glyph in the atlas
     #[location = 2]
-    pub background: data::f32_f32_f32_f32,
+    pub texture: data::f32_f32_f32_f32,
     #[location = 3]
+    pub background: data::f32_f32_f32_f32,
+    #[location = 4]
     pub foreground: data::f32_f32_f32_f32,
-}
\ No newline at end of file
+    // bottom left and top right of the glyph inside its cell, from 0 to 1
+    #[location = 5]
+    pub glyph_quad: data::f32_f32_f32_f32,
+}
+
+impl GlyphInstance {
+    // A cell nobody drew into. The glyph quad lies outside the cell and the background is
+    // transparent, so the fragment shader discards all of it.
+    pub fn empty(cell: f32, layer: f32) -> Self {
+        GlyphInstance {
+            cell: (cell, layer, 0.0).into(),
+            texture: (0.0, 0.0, 0.0, 0.0).into(),
+            background: (0.0, 0.0, 0.0, 0.0).into(),
+            foreground: (0.0, 0.0, 0.0, 0.0).into(),
+            glyph_quad: (2.0, 2.0, 3.0, 3.0).into(),
+        }
+    }
+}
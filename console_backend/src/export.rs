@@ -0,0 +1,216 @@
+use std::fmt::Write;
+use crate::cell_buffer::CellBuffer;
+use crate::style::Style;
+use crate::Color;
+
+// A cell as it ends up on screen once its layers are drawn over each other
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct FlatCell {
+    character: char,
+    foreground: Color,
+    background: Color,
+    style: Style,
+}
+
+impl FlatCell {
+    // Blank cells only show their background, so they can join a run of any foreground
+    fn continues(&self, run: &FlatCell) -> bool {
+        let blank = self.character == ' '
+            && !self.style.contains(Style::UNDERLINE)
+            && !self.style.contains(Style::STRIKETHROUGH);
+        run.background == self.background
+            && (blank || (run.foreground == self.foreground && run.style == self.style))
+    }
+}
+
+// Follows the renderers, a layer's background covers whatever was drawn below it unless it's
+// transparent, and its character does unless it's a space
+fn flatten(cells: &CellBuffer, x: u32, y: u32) -> FlatCell {
+    let mut flat = FlatCell {
+        character: ' ',
+        foreground: cells.default_background(),
+        background: cells.default_background(),
+        style: Style::NONE,
+    };
+    for layer in 0..cells.layer_count() {
+        let glyph = match cells.get_cell(x, y, layer) {
+            Some(glyph) => glyph,
+            None => continue,
+        };
+        let (background, foreground) = glyph.display_colors();
+        if background.a >= 0.1 {
+            flat.background = background;
+            flat.character = ' ';
+        }
+        if glyph.character != ' ' {
+            flat.character = glyph.character;
+            // The glyph keeps the atlas' own white when the foreground is transparent
+            flat.foreground = if foreground.a < 0.1 { Color::from_int(255, 255, 255, 1.0) } else { foreground };
+            flat.style = glyph.style;
+        }
+    }
+    flat
+}
+
+// Rows of flattened cells, top row first
+fn rows(cells: &CellBuffer) -> Vec<Vec<FlatCell>> {
+    let (width, height) = cells.dimensions();
+    (0..height).rev()
+        .map(|y| (0..width).map(|x| flatten(cells, x, y)).collect())
+        .collect()
+}
+
+fn rgb(color: Color) -> (u8, u8, u8) {
+    let channel = |value: f32| (value.max(0.0).min(1.0) * 255.0).round() as u8;
+    (channel(color.x), channel(color.y), channel(color.z))
+}
+
+// What's on screen as plain text, top row first, trailing spaces left out
+pub fn to_plain_text(cells: &CellBuffer) -> String {
+    rows(cells).iter()
+        .map(|row| row.iter().map(|cell| cell.character).collect::<String>().trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// For terminals with 24 bit color, every line ends with a reset so it can be pasted on its own
+pub fn to_ansi(cells: &CellBuffer) -> String {
+    let mut out = String::new();
+    for row in rows(cells) {
+        let mut run: Option<FlatCell> = None;
+        for cell in row {
+            if !run.map_or(false, |run| cell.continues(&run)) {
+                let (fr, fg, fb) = rgb(cell.foreground);
+                let (br, bg, bb) = rgb(cell.background);
+                write!(out, "\x1b[0;38;2;{};{};{};48;2;{};{};{}", fr, fg, fb, br, bg, bb).unwrap();
+                for &(style, code) in &[(Style::BOLD, 1), (Style::ITALIC, 3), (Style::UNDERLINE, 4), (Style::STRIKETHROUGH, 9)] {
+                    if cell.style.contains(style) {
+                        write!(out, ";{}", code).unwrap();
+                    }
+                }
+                out.push('m');
+                run = Some(cell);
+            }
+            out.push(cell.character);
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+// A whole HTML page holding a <pre>, runs of cells that look the same share a <span>
+pub fn to_html(cells: &CellBuffer) -> String {
+    let (r, g, b) = rgb(cells.default_background());
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n</head>\n<body>\n");
+    write!(out, "<pre style=\"background-color:#{:02x}{:02x}{:02x};font-family:monospace;line-height:1;\">", r, g, b).unwrap();
+    for row in rows(cells) {
+        let mut run: Option<FlatCell> = None;
+        for cell in row {
+            if !run.map_or(false, |run| cell.continues(&run)) {
+                if run.is_some() {
+                    out.push_str("</span>");
+                }
+                out.push_str("<span style=\"");
+                out.push_str(&html_style(&cell));
+                out.push_str("\">");
+                run = Some(cell);
+            }
+            match cell.character {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                c => out.push(c),
+            }
+        }
+        if run.is_some() {
+            out.push_str("</span>");
+        }
+        out.push('\n');
+    }
+    out.push_str("</pre>\n</body>\n</html>\n");
+    out
+}
+
+fn html_style(cell: &FlatCell) -> String {
+    let (fr, fg, fb) = rgb(cell.foreground);
+    let (br, bg, bb) = rgb(cell.background);
+    let mut style = format!("color:#{:02x}{:02x}{:02x};background-color:#{:02x}{:02x}{:02x};", fr, fg, fb, br, bg, bb);
+    if cell.style.contains(Style::BOLD) {
+        style.push_str("font-weight:bold;");
+    }
+    if cell.style.contains(Style::ITALIC) {
+        style.push_str("font-style:italic;");
+    }
+    let lines: Vec<&str> = [(Style::UNDERLINE, "underline"), (Style::STRIKETHROUGH, "line-through")].iter()
+        .filter(|(flag, _)| cell.style.contains(*flag))
+        .map(|(_, line)| *line)
+        .collect();
+    if !lines.is_empty() {
+        write!(style, "text-decoration:{};", lines.join(" ")).unwrap();
+    }
+    style
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::colors;
+
+    #[test]
+    fn layers_are_flattened_like_the_renderers_draw_them() {
+        let mut cells = CellBuffer::new((4, 1), *colors::BLACK);
+        cells.put_text("abcd", 0, 0, *colors::WHITE, None, 0);
+        // A transparent space leaves what's below, an opaque one hides it
+        cells.put_char(' ', 0, 0, *colors::WHITE, Some(*colors::CLEAR), 1);
+        cells.put_char(' ', 1, 0, *colors::WHITE, Some(*colors::NAVY), 1);
+        cells.put_char('X', 2, 0, *colors::WHITE, Some(*colors::CLEAR), 2);
+        assert_eq!(to_plain_text(&cells), "a Xd");
+
+        let flat = flatten(&cells, 2, 0);
+        assert_eq!((flat.character, flat.background), ('X', *colors::BLACK));
+        assert_eq!(flatten(&cells, 1, 0).background, *colors::NAVY);
+    }
+
+    #[test]
+    fn plain_text_keeps_every_row_but_not_trailing_spaces() {
+        let mut cells = CellBuffer::new((4, 3), *colors::BLACK);
+        cells.put_text("ab", 1, 2, *colors::WHITE, None, 0);
+        assert_eq!(to_plain_text(&cells), " ab\n\n");
+    }
+
+    #[test]
+    fn ansi_starts_a_run_per_change_and_resets_every_row() {
+        let mut cells = CellBuffer::new((3, 2), *colors::BLACK);
+        cells.put_text("ab", 0, 1, *colors::WHITE, None, 0);
+        cells.put_char_styled('c', 2, 1, Color::from_int(255, 0, 0, 1.0), None, Style::BOLD | Style::UNDERLINE, 0);
+        let ansi = to_ansi(&cells);
+        let lines: Vec<&str> = ansi.lines().collect();
+        assert_eq!(lines, vec![
+            "\x1b[0;38;2;255;255;255;48;2;0;0;0mab\x1b[0;38;2;255;0;0;48;2;0;0;0;1;4mc\x1b[0m",
+            "\x1b[0;38;2;0;0;0;48;2;0;0;0m   \x1b[0m",
+        ]);
+        assert!(ansi.ends_with("\x1b[0m\n"));
+    }
+
+    #[test]
+    fn html_escapes_markup_characters() {
+        let mut cells = CellBuffer::new((5, 1), *colors::BLACK);
+        cells.put_text("<&>", 0, 0, *colors::WHITE, None, 0);
+        let html = to_html(&cells);
+        assert!(html.contains(">&lt;&amp;&gt;  </span>"));
+        assert!(!html.contains("<&>"));
+        assert!(html.contains("<pre style=\"background-color:#000000;"));
+    }
+
+    #[test]
+    fn html_has_a_line_per_row() {
+        let mut cells = CellBuffer::new((2, 3), *colors::BLACK);
+        cells.put_text_styled("i", 0, 2, *colors::WHITE, None, Style::ITALIC, 0);
+        let html = to_html(&cells);
+        let pre = html.find("<pre").unwrap();
+        let body = &html[pre + html[pre..].find("\">").unwrap() + 2..html.find("</pre>").unwrap()];
+        assert_eq!(body.lines().count(), 3);
+        assert!(body.starts_with("<span style=\"color:#ffffff;background-color:#000000;font-style:italic;\">i"));
+    }
+}
@@ -0,0 +1,230 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use font_renderer::{load_bitmap_with_charset, load_sdf_with_charset, load_tileset, atlas_cell_metrics, BoundingBox, Charset, GlyphCache, CacheUpdate, TilesetLayout};
+use image::GenericImageView;
+use crate::resources::{self, Resources};
+use crate::render_gl::texture::Texture;
+
+pub const DEFAULT_REPLACEMENT: char = '?';
+pub const DEFAULT_FONT: &str = "droid-sans-mono.ttf";
+
+const GLYPH_HEIGHT: f32 = 100.0;
+const CACHE_DIMENSIONS: (u32, u32) = (2048, 2048);
+
+#[derive(Debug, Fail)]
+pub enum FontError {
+    #[fail(display = "Failed to load font {}", name)]
+    ResourceLoad { name: String, #[cause] inner: resources::Error },
+    #[fail(display = "Font {} is not a TrueType font", name)]
+    InvalidFont { name: String },
+}
+
+// Told about each character the font is missing, once per font
+pub type MissingGlyphHook = Box<dyn FnMut(char, char)>;
+
+struct MissingGlyphs {
+    reported: HashSet<char>,
+    hook: MissingGlyphHook,
+}
+
+#[derive(Clone)]
+pub struct FontInfo {
+    pub(crate) texture: Rc<Texture>,
+    pub(crate) glyph_map: HashMap<char, BoundingBox>,
+    pub(crate) texture_scale: (i32, i32),
+    replacement: char,
+    // Shared with every console using this font so a character is only reported once
+    missing: Rc<RefCell<MissingGlyphs>>,
+    // Glyphs rasterized on first use, looked up before glyph_map
+    cache: Option<Rc<RefCell<GlyphCache>>>,
+    // Bumped whenever the cache moves glyphs around, invalidating every lookup made before
+    generation: Rc<Cell<u32>>,
+    // The atlas holds distances to the outline instead of coverage
+    sdf: bool,
+    // Pixels a cell takes up in the atlas
+    cell_size: (f32, f32),
+}
+
+impl FontInfo {
+    pub fn from_res(res: &Resources, gl: &gl::Gl, name: &str) -> Result<Self, failure::Error> {
+        FontInfo::from_res_with_charset(res, gl, name, &Charset::default())
+    }
+
+    // Bakes every character of the charset the font has into a single texture up front
+    pub fn from_res_with_charset(res: &Resources, gl: &gl::Gl, name: &str, charset: &Charset) -> Result<Self, failure::Error> {
+        let font_bytes = load_font_bytes(res, name)?;
+        let cell = atlas_cell_metrics(&font_bytes)
+            .ok_or_else(|| FontError::InvalidFont { name: name.into() })?;
+        let (font_img, glyph_map) = load_bitmap_with_charset(font_bytes, charset)
+            .ok_or_else(|| FontError::InvalidFont { name: name.into() })?;
+        let texture_scale_u32 = font_img.dimensions();
+        let texture = Texture::from_img(gl, font_img, gl::RGBA)?;
+        let texture_scale = (texture_scale_u32.0 as i32, texture_scale_u32.1 as i32);
+        Ok(FontInfo::with_texture(texture, glyph_map, texture_scale, (cell.width, cell.height), None))
+    }
+
+    // Distance field atlas, drawn with the glyph_sdf shader so it stays sharp at any size
+    pub fn sdf_from_res(res: &Resources, gl: &gl::Gl, name: &str, charset: &Charset) -> Result<Self, failure::Error> {
+        let font_bytes = load_font_bytes(res, name)?;
+        let cell = atlas_cell_metrics(&font_bytes)
+            .ok_or_else(|| FontError::InvalidFont { name: name.into() })?;
+        let (font_img, glyph_map) = load_sdf_with_charset(font_bytes, charset)
+            .ok_or_else(|| FontError::InvalidFont { name: name.into() })?;
+        let texture_scale_u32 = font_img.dimensions();
+        let texture = Texture::from_img(gl, font_img, gl::RGBA)?;
+        texture.set_min_filter(gl::LINEAR);
+        let texture_scale = (texture_scale_u32.0 as i32, texture_scale_u32.1 as i32);
+        let mut font_info = FontInfo::with_texture(texture, glyph_map, texture_scale, (cell.width, cell.height), None);
+        font_info.sdf = true;
+        Ok(font_info)
+    }
+
+    // Rasterizes glyphs as they are first drawn, starting with the charset
+    pub fn cached_from_res(res: &Resources, gl: &gl::Gl, name: &str, charset: &Charset) -> Result<Self, failure::Error> {
+        let font_bytes = load_font_bytes(res, name)?;
+        let cache = GlyphCache::new(font_bytes, GLYPH_HEIGHT, CACHE_DIMENSIONS)
+            .ok_or_else(|| FontError::InvalidFont { name: name.into() })?;
+        let texture = Texture::new_empty(gl, CACHE_DIMENSIONS);
+        let texture_scale = (CACHE_DIMENSIONS.0 as i32, CACHE_DIMENSIONS.1 as i32);
+        let cell = cache.cell_metrics();
+        let font_info = FontInfo::with_texture(texture, HashMap::new(), texture_scale, (cell.width, cell.height), Some(cache));
+        font_info.cache_glyphs(charset.chars().iter().cloned());
+        Ok(font_info)
+    }
+
+    // Tiles of a sheet like the 16x16 CP437 ones most roguelike art ships as
+    pub fn from_tileset(res: &Resources, gl: &gl::Gl, name: &str, layout: TilesetLayout, codepage: &[char]) -> Result<Self, failure::Error> {
+        let tileset = res.load_image(name)
+            .map_err(|inner| FontError::ResourceLoad { name: name.into(), inner })?;
+        let (tile_width, tile_height) = layout.tile_size(tileset.dimensions())?;
+        let (font_img, glyph_map) = load_tileset(&tileset, layout, codepage)?;
+        let texture_scale_u32 = font_img.dimensions();
+        let texture = Texture::from_img(gl, font_img, gl::RGBA)?;
+        texture.set_mag_filter(gl::NEAREST);
+        let texture_scale = (texture_scale_u32.0 as i32, texture_scale_u32.1 as i32);
+        Ok(FontInfo::with_texture(texture, glyph_map, texture_scale, (tile_width as f32, tile_height as f32), None))
+    }
+
+    fn with_texture(texture: Texture, glyph_map: HashMap<char, BoundingBox>, texture_scale: (i32, i32), cell_size: (f32, f32), cache: Option<GlyphCache>) -> Self {
+        FontInfo {
+            glyph_map,
+            texture_scale,
+            texture: Rc::new(texture),
+            replacement: DEFAULT_REPLACEMENT,
+            missing: Rc::new(RefCell::new(MissingGlyphs {
+                reported: HashSet::new(),
+                // Silent until someone asks, see Console::on_missing_glyph
+                hook: Box::new(|_, _| {}),
+            })),
+            cache: cache.map(|cache| Rc::new(RefCell::new(cache))),
+            generation: Rc::new(Cell::new(0)),
+            sdf: false,
+            cell_size,
+        }
+    }
+
+    pub fn can_render(&self, c: char) -> bool {
+        match &self.cache {
+            Some(cache) => cache.borrow().has_glyph(c),
+            None => self.glyph_map.contains_key(&c),
+        }
+    }
+
+    pub fn cell_size(&self) -> (f32, f32) {
+        self.cell_size
+    }
+
+    pub fn is_sdf(&self) -> bool {
+        self.sdf
+    }
+
+    pub fn replacement(&self) -> char {
+        self.replacement
+    }
+
+    pub fn set_replacement(&mut self, replacement: char) {
+        self.replacement = replacement;
+    }
+
+    pub fn on_missing_glyph(&self, hook: MissingGlyphHook) {
+        self.missing.borrow_mut().hook = hook;
+    }
+
+    // Whether anything besides this FontInfo holds on to the texture
+    pub(crate) fn is_shared(&self) -> bool {
+        Rc::strong_count(&self.texture) > 1
+    }
+
+    pub(crate) fn generation(&self) -> u32 {
+        self.generation.get()
+    }
+
+    // Makes sure the cache holds every character that's about to be drawn, a no-op for baked fonts
+    pub(crate) fn cache_glyphs<I>(&self, chars: I) where I: IntoIterator<Item = char> {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return,
+        };
+        let texture = &self.texture;
+        let replacement = self.replacement;
+        let chars = chars.into_iter().chain(Some(replacement));
+        let result = cache.borrow_mut().cache(chars, |region, pixels| {
+            texture.update((region.x, region.y), (region.width, region.height), pixels);
+        });
+        match result {
+            Ok(CacheUpdate::Unchanged) | Ok(CacheUpdate::Added) => {},
+            Ok(CacheUpdate::Reordered) => self.generation.set(self.generation.get().wrapping_add(1)),
+            // Whatever didn't fit is rejected by the cache, so it's drawn with the replacement
+            // glyph and reported to the missing glyph hook once it's looked up
+            Err(_) => {},
+        }
+    }
+
+    // Falls back to the replacement glyph, reporting the first miss of every character
+    pub(crate) fn bounding_box(&self, c: char) -> BoundingBox {
+        if let Some(cache) = &self.cache {
+            let cache = cache.borrow();
+            if let Some(bounding_box) = cache.get(c) {
+                return bounding_box.clone();
+            }
+            self.report_missing(c);
+            return cache.get(self.replacement)
+                .or_else(|| cache.get(' '))
+                .cloned()
+                .unwrap_or_else(BoundingBox::blank);
+        }
+        if let Some(bounding_box) = self.glyph_map.get(&c) {
+            return bounding_box.clone();
+        }
+        self.report_missing(c);
+        find_glyph(&self.glyph_map, c, self.replacement)
+            .cloned()
+            .unwrap_or_else(BoundingBox::blank)
+    }
+
+    fn report_missing(&self, c: char) {
+        let mut missing = self.missing.borrow_mut();
+        if missing.reported.insert(c) {
+            (missing.hook)(c, self.replacement);
+        }
+    }
+}
+
+fn load_font_bytes(res: &Resources, name: &str) -> Result<Vec<u8>, FontError> {
+    res.load_bytes_from_file(name)
+        .map_err(|inner| FontError::ResourceLoad { name: name.into(), inner })
+}
+
+// Fonts given as images are tilesets, anything else is a TTF
+pub(crate) fn is_tileset(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.ends_with(".png") || name.ends_with(".bmp")
+}
+
+// The glyph for c, else the replacement, else a blank cell
+pub(crate) fn find_glyph(glyph_map: &HashMap<char, BoundingBox>, c: char, replacement: char) -> Option<&BoundingBox> {
+    glyph_map.get(&c)
+        .or_else(|| glyph_map.get(&replacement))
+        .or_else(|| glyph_map.get(&' '))
+}
@@ -0,0 +1,73 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use font_renderer::{Charset, TilesetLayout};
+use crate::font_info::{FontInfo, is_tileset};
+use crate::resources::Resources;
+
+// Everything that decides what texture a font file turns into
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct FontKey {
+    pub name: String,
+    pub charset: Charset,
+    pub glyph_cache: bool,
+    pub sdf: bool,
+    pub tileset_layout: TilesetLayout,
+    pub codepage: Vec<char>,
+}
+
+impl FontKey {
+    pub fn load(&self, res: &Resources, gl: &gl::Gl) -> Result<FontInfo, failure::Error> {
+        if is_tileset(&self.name) {
+            FontInfo::from_tileset(res, gl, &self.name, self.tileset_layout, &self.codepage)
+        } else if self.sdf {
+            FontInfo::sdf_from_res(res, gl, &self.name, &self.charset)
+        } else if self.glyph_cache {
+            FontInfo::cached_from_res(res, gl, &self.name, &self.charset)
+        } else {
+            FontInfo::from_res_with_charset(res, gl, &self.name, &self.charset)
+        }
+    }
+}
+
+// Fonts loaded so far, consoles built with the same registry and font share one texture.
+// Clones refer to the same registry.
+#[derive(Clone, Default)]
+pub struct FontRegistry {
+    fonts: Rc<RefCell<Vec<(FontKey, FontInfo)>>>,
+}
+
+impl FontRegistry {
+    pub fn new() -> Self {
+        FontRegistry::default()
+    }
+
+    // The first font loaded from the file name
+    pub fn get(&self, name: &str) -> Option<FontInfo> {
+        self.fonts.borrow()
+            .iter()
+            .find(|(key, _)| key.name == name)
+            .map(|(_, font_info)| font_info.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.fonts.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fonts.borrow().is_empty()
+    }
+
+    // Drops fonts no console uses anymore
+    pub fn purge_unused(&self) {
+        self.fonts.borrow_mut().retain(|(_, font_info)| font_info.is_shared());
+    }
+
+    pub(crate) fn load(&self, res: &Resources, gl: &gl::Gl, key: FontKey) -> Result<FontInfo, failure::Error> {
+        if let Some((_, font_info)) = self.fonts.borrow().iter().find(|(loaded, _)| *loaded == key) {
+            return Ok(font_info.clone());
+        }
+        let font_info = key.load(res, gl)?;
+        self.fonts.borrow_mut().push((key, font_info.clone()));
+        Ok(font_info)
+    }
+}
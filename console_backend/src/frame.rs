@@ -0,0 +1,39 @@
+// Characters draw_frame builds a box out of
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameStyle {
+    Single,
+    Double,
+    Rounded,
+    Heavy,
+    Ascii,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FrameChars {
+    pub horizontal: char,
+    pub vertical: char,
+    pub top_left: char,
+    pub top_right: char,
+    pub bottom_left: char,
+    pub bottom_right: char,
+}
+
+impl FrameStyle {
+    pub fn chars(self) -> FrameChars {
+        let (horizontal, vertical, top_left, top_right, bottom_left, bottom_right) = match self {
+            FrameStyle::Single => ('─', '│', '┌', '┐', '└', '┘'),
+            FrameStyle::Double => ('═', '║', '╔', '╗', '╚', '╝'),
+            FrameStyle::Rounded => ('─', '│', '╭', '╮', '╰', '╯'),
+            FrameStyle::Heavy => ('━', '┃', '┏', '┓', '┗', '┛'),
+            FrameStyle::Ascii => ('-', '|', '+', '+', '+', '+'),
+        };
+        FrameChars {
+            horizontal,
+            vertical,
+            top_left,
+            top_right,
+            bottom_left,
+            bottom_right,
+        }
+    }
+}
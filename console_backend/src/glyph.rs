@@ -1,18 +1,38 @@
 use crate::render_gl::{data};
+use crate::style::Style;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Glyph {
     pub character: char,
     pub background: data::f32_f32_f32_f32,
     pub foreground: data::f32_f32_f32_f32,
+    pub style: Style,
 }
 
 impl Glyph {
     pub fn new(character: char, background:  data::f32_f32_f32_f32, foreground: data::f32_f32_f32_f32) -> Self {
+        Glyph::styled(character, background, foreground, Style::NONE)
+    }
+
+    pub fn styled(character: char, background:  data::f32_f32_f32_f32, foreground: data::f32_f32_f32_f32, style: Style) -> Self {
         Glyph{
             character,
             background,
             foreground,
+            style,
+        }
+    }
+
+    // Background and foreground as drawn, with reverse and dim applied
+    pub fn display_colors(&self) -> (data::f32_f32_f32_f32, data::f32_f32_f32_f32) {
+        let (background, mut foreground) = if self.style.contains(Style::REVERSE) {
+            (self.foreground, self.background)
+        } else {
+            (self.background, self.foreground)
+        };
+        if self.style.contains(Style::DIM) {
+            foreground = (foreground.x * 0.5, foreground.y * 0.5, foreground.z * 0.5, foreground.a).into();
         }
+        (background, foreground)
     }
 }
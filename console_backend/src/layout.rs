@@ -0,0 +1,164 @@
+// Which point of its parent a console sticks to, the console's own matching point goes there
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    // -1, 0 or 1 per axis, y pointing up
+    pub(crate) fn direction(self) -> (f32, f32) {
+        match self {
+            Anchor::TopLeft => (-1.0, 1.0),
+            Anchor::Top => (0.0, 1.0),
+            Anchor::TopRight => (1.0, 1.0),
+            Anchor::Left => (-1.0, 0.0),
+            Anchor::Center => (0.0, 0.0),
+            Anchor::Right => (1.0, 0.0),
+            Anchor::BottomLeft => (-1.0, -1.0),
+            Anchor::Bottom => (0.0, -1.0),
+            Anchor::BottomRight => (1.0, -1.0),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Length {
+    // Cells of the console being placed
+    Cells(f32),
+    // Fraction of the parent's width or height
+    Fraction(f32),
+}
+
+// An area of the screen in normalized device coordinates
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Region {
+    pub center: (f32, f32),
+    pub half_size: (f32, f32),
+}
+
+impl Region {
+    pub fn screen() -> Self {
+        Region {
+            center: (0.0, 0.0),
+            half_size: (1.0, 1.0),
+        }
+    }
+}
+
+// Where a console goes inside its parent. Margins push it inwards from the anchored edges,
+// for centered axes they move it right or up.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Layout {
+    pub anchor: Anchor,
+    pub margin: (Length, Length),
+    // Fraction of the parent's width and height
+    pub size: (f32, f32),
+}
+
+impl Layout {
+    pub fn new(anchor: Anchor, size: (f32, f32)) -> Self {
+        Layout {
+            anchor,
+            margin: (Length::Cells(0.0), Length::Cells(0.0)),
+            size,
+        }
+    }
+
+    pub fn margin(mut self, margin: (Length, Length)) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    // Screen scaling and offset for a console with dimensions cells inside parent. The
+    // scale modifier shrinks it towards its anchor rather than its center.
+    pub(crate) fn resolve(&self, parent: Region, dimensions: (u32, u32), scale_modifier: (f32, f32)) -> ((f32, f32), (f32, f32)) {
+        let scaling = (self.size.0 * parent.half_size.0, self.size.1 * parent.half_size.1);
+        let half_size = (scaling.0 * scale_modifier.0, scaling.1 * scale_modifier.1);
+        let margin = |length: Length, cells: u32, parent_half: f32, half: f32| match length {
+            Length::Cells(count) => count * 2.0 * half / cells.max(1) as f32,
+            Length::Fraction(fraction) => fraction * 2.0 * parent_half,
+        };
+        let margin = (margin(self.margin.0, dimensions.0, parent.half_size.0, half_size.0),
+                      margin(self.margin.1, dimensions.1, parent.half_size.1, half_size.1));
+        let (direction_x, direction_y) = self.anchor.direction();
+        let position = |direction: f32, center: f32, parent_half: f32, half: f32, margin: f32| {
+            if direction == 0.0 {
+                center + margin
+            } else {
+                center + direction * (parent_half - half - margin)
+            }
+        };
+        let offset = (position(direction_x, parent.center.0, parent.half_size.0, half_size.0, margin.0),
+                      position(direction_y, parent.center.1, parent.half_size.1, half_size.1, margin.1));
+        (scaling, offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARENT: Region = Region {
+        center: (0.2, -0.1),
+        half_size: (0.5, 0.4),
+    };
+
+    fn assert_close(actual: (f32, f32), expected: (f32, f32)) {
+        assert!((actual.0 - expected.0).abs() < 1e-5 && (actual.1 - expected.1).abs() < 1e-5,
+                "{:?} isn't {:?}", actual, expected);
+    }
+
+    #[test]
+    fn anchors_stick_to_their_point_of_the_parent() {
+        let expected = [
+            (Anchor::TopLeft, (-0.05, 0.1)),
+            (Anchor::Top, (0.2, 0.1)),
+            (Anchor::TopRight, (0.45, 0.1)),
+            (Anchor::Left, (-0.05, -0.1)),
+            (Anchor::Center, (0.2, -0.1)),
+            (Anchor::Right, (0.45, -0.1)),
+            (Anchor::BottomLeft, (-0.05, -0.3)),
+            (Anchor::Bottom, (0.2, -0.3)),
+            (Anchor::BottomRight, (0.45, -0.3)),
+        ];
+        for &(anchor, offset) in &expected {
+            let (scaling, actual) = Layout::new(anchor, (0.5, 0.5)).resolve(PARENT, (4, 2), (1.0, 1.0));
+            assert_close(scaling, (0.25, 0.2));
+            assert_close(actual, offset);
+        }
+    }
+
+    #[test]
+    fn margins_push_inwards_or_right_and_up() {
+        // A cell is 0.125 wide, a tenth of the parent 0.08 high
+        let margin = (Length::Cells(1.0), Length::Fraction(0.1));
+        let offset = |anchor| Layout::new(anchor, (0.5, 0.5)).margin(margin).resolve(PARENT, (4, 2), (1.0, 1.0)).1;
+        assert_close(offset(Anchor::TopRight), (0.325, 0.02));
+        assert_close(offset(Anchor::BottomLeft), (0.075, -0.22));
+        assert_close(offset(Anchor::Center), (0.325, -0.02));
+    }
+
+    #[test]
+    fn scale_modifiers_shrink_towards_the_anchor() {
+        let (scaling, offset) = Layout::new(Anchor::BottomLeft, (0.5, 0.5)).resolve(PARENT, (4, 2), (0.5, 1.0));
+        assert_close(scaling, (0.25, 0.2));
+        assert_close(offset, (-0.175, -0.3));
+        let (_, offset) = Layout::new(Anchor::Center, (0.5, 0.5)).resolve(PARENT, (4, 2), (0.5, 1.0));
+        assert_close(offset, (0.2, -0.1));
+    }
+
+    #[test]
+    fn a_full_size_layout_covers_its_parent() {
+        let (scaling, offset) = Layout::new(Anchor::BottomLeft, (1.0, 1.0)).resolve(Region::screen(), (80, 50), (1.0, 1.0));
+        assert_close(scaling, (1.0, 1.0));
+        assert_close(offset, (0.0, 0.0));
+    }
+}
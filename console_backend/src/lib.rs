@@ -1,23 +1,58 @@
 #[macro_use] extern crate failure;
 #[macro_use] extern crate render_gl_derive;
 #[macro_use] extern crate lazy_static;
+mod assets;
 mod console;
+mod console_root;
+mod cell_buffer;
+mod backend;
+mod font_info;
+mod font_registry;
+mod rect;
 mod color;
 mod console_vertex;
 mod glyph;
+mod style;
+mod markup;
+mod text_layout;
+mod frame;
+mod blit;
+mod layout;
+mod export;
+mod rexpaint;
 pub mod resources;
 mod render_gl;
 mod renderable;
+mod software_renderer;
 
 pub use render_gl::data;
 pub use console::{
     Console,
     ConsoleBuilder,
     Transformer,
+    ResizePolicy,
+    ResizeHook,
 };
+pub use console_root::{ConsoleRoot, ConsoleHandle};
 
 pub use color::{Color, colors};
 pub use glyph::Glyph;
+pub use style::Style;
+pub use markup::{Span, Palette, parse_markup};
+pub use text_layout::{Align, wrap_text, wrap_spans};
+pub use frame::{FrameStyle, FrameChars};
+pub use cell_buffer::{CellBuffer, Damage, Snapshot, DEFAULT_LAYERS};
+pub use blit::blit;
+pub use export::{to_plain_text, to_ansi, to_html};
+pub use rexpaint::{read_xp, write_xp, XpError};
+pub use rect::Rect;
+pub use layout::{Anchor, Layout, Length, Region};
+pub use backend::{RenderBackend, HeadlessBackend, Placement};
+pub use font_info::{FontInfo, FontError, MissingGlyphHook, DEFAULT_FONT, DEFAULT_REPLACEMENT};
+pub use font_registry::FontRegistry;
+pub use assets::Assets;
+pub use software_renderer::SoftwareRenderer;
+pub use font_renderer::{Charset, TilesetLayout, CP437, TOFU};
 pub use render_gl::{
     camera::Camera,
     buffer::{VertexArray, ArrayBuffer, ElementArrayBuffer},
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use crate::color::{Color, colors};
+use crate::style::Style;
+
+// A run of text drawn with the same colors and style. Anything left as None falls back to
+// whatever the caller prints the spans with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub style: Style,
+}
+
+impl Span {
+    pub fn new(text: &str) -> Self {
+        Span {
+            text: text.to_string(),
+            foreground: None,
+            background: None,
+            style: Style::NONE,
+        }
+    }
+
+    pub fn colored(text: &str, foreground: Color) -> Self {
+        Span::new(text).foreground(foreground)
+    }
+
+    pub fn foreground(mut self, foreground: Color) -> Self {
+        self.foreground = Some(foreground);
+        self
+    }
+
+    pub fn background(mut self, background: Color) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    pub fn style(mut self, style: Style) -> Self {
+        self.style |= style;
+        self
+    }
+
+    // Width in cells
+    pub fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+}
+
+impl<'a> From<&'a str> for Span {
+    fn from(text: &'a str) -> Self {
+        Span::new(text)
+    }
+}
+
+// Color names markup can refer to. The default palette knows everything in colors,
+// games add their own theme on top.
+#[derive(Debug, Clone)]
+pub struct Palette {
+    colors: HashMap<String, Color>,
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        Palette {
+            colors: HashMap::new(),
+        }
+    }
+
+    // Names are case insensitive
+    pub fn insert(&mut self, name: &str, color: Color) -> &mut Self {
+        self.colors.insert(name.to_lowercase(), color);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<Color> {
+        self.colors.get(&name.to_lowercase()).cloned()
+    }
+
+    // A palette name or a #rrggbb / #rrggbbaa hex code
+    pub fn resolve(&self, name: &str) -> Option<Color> {
+        if name.starts_with('#') {
+            parse_hex(&name[1..])
+        } else {
+            self.get(name)
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        let mut palette = Palette::new();
+        palette
+            .insert("red", *colors::RED)
+            .insert("dark_red", *colors::DARK_RED)
+            .insert("dark_green", *colors::DARK_GREEN)
+            .insert("navy", *colors::NAVY)
+            .insert("dark_indigo", *colors::DARK_INDIGO)
+            .insert("clear", *colors::CLEAR)
+            .insert("black", *colors::BLACK)
+            .insert("white", *colors::WHITE)
+            .insert("desaturated_green", *colors::DESATURATED_GREEN)
+            .insert("light_green", *colors::LIGHT_GREEN)
+            .insert("light_slate_blue", *colors::LIGHT_SLATE_BLUE)
+            .insert("light_yellow", *colors::LIGHT_YELLOW)
+            .insert("yellow", *colors::YELLOW)
+            .insert("dark_yellow", *colors::DARK_YELLOW);
+        palette
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if (hex.len() != 6 && hex.len() != 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |index: usize| i32::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(3)? as f32 / 255.0 } else { 1.0 };
+    Some(Color::from_int(channel(0)?, channel(1)?, channel(2)?, alpha))
+}
+
+fn parse_style(name: &str) -> Option<Style> {
+    match name {
+        "bold" | "b" => Some(Style::BOLD),
+        "italic" | "i" => Some(Style::ITALIC),
+        "underline" | "u" => Some(Style::UNDERLINE),
+        "strikethrough" | "s" => Some(Style::STRIKETHROUGH),
+        "reverse" => Some(Style::REVERSE),
+        "dim" => Some(Style::DIM),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy)]
+struct SpanState {
+    foreground: Option<Color>,
+    background: Option<Color>,
+    style: Style,
+}
+
+impl SpanState {
+    // None when the tag isn't one we know, so it gets printed as is
+    fn apply(self, tag: &str, palette: &Palette) -> Option<SpanState> {
+        let tag = tag.trim().to_lowercase();
+        let mut state = self;
+        if tag.starts_with("bg:") {
+            state.background = Some(palette.resolve(&tag[3..])?);
+        } else if let Some(style) = parse_style(&tag) {
+            state.style |= style;
+        } else {
+            state.foreground = Some(palette.resolve(&tag)?);
+        }
+        Some(state)
+    }
+}
+
+// Splits markup like "{red}orc{/} attacks you" into spans.
+// {name} or {#rrggbb} sets the foreground, {bg:name} the background, {bold}, {italic},
+// {underline}, {strikethrough}, {reverse} and {dim} add a style and {/} undoes the last tag.
+// {{ and }} are literal braces, so is a lone }. Tags that don't resolve are kept as text.
+pub fn parse_markup(markup: &str, palette: &Palette) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut stack = vec![SpanState { foreground: None, background: None, style: Style::NONE }];
+    let mut text = String::new();
+    let mut rest = markup;
+
+    let flush = |text: &mut String, spans: &mut Vec<Span>, state: &SpanState| {
+        if !text.is_empty() {
+            spans.push(Span {
+                text: std::mem::replace(text, String::new()),
+                foreground: state.foreground,
+                background: state.background,
+                style: state.style,
+            });
+        }
+    };
+
+    while let Some(start) = rest.find(|c| c == '{' || c == '}') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            text.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        if rest.starts_with('}') {
+            text.push('}');
+            rest = &rest[1..];
+            continue;
+        }
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        let tag = &rest[1..end];
+        let current = *stack.last().unwrap();
+        if tag.trim() == "/" {
+            flush(&mut text, &mut spans, &current);
+            if stack.len() > 1 {
+                stack.pop();
+            }
+        } else if let Some(state) = current.apply(tag, palette) {
+            flush(&mut text, &mut spans, &current);
+            stack.push(state);
+        } else {
+            text.push_str(&rest[..=end]);
+        }
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    flush(&mut text, &mut spans, stack.last().unwrap());
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(spans: &[Span]) -> Vec<&str> {
+        spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    #[test]
+    fn tags_color_the_text_until_popped() {
+        let palette = Palette::default();
+        let spans = parse_markup("a {white}b{/} c", &palette);
+        assert_eq!(texts(&spans), vec!["a ", "b", " c"]);
+        assert_eq!(spans[0].foreground, None);
+        assert_eq!(spans[1].foreground, Some(*colors::WHITE));
+        assert_eq!(spans[2].foreground, None);
+    }
+
+    #[test]
+    fn tags_nest() {
+        let palette = Palette::default();
+        let spans = parse_markup("{bg:navy}{b}x{/}y{/}z", &palette);
+        assert_eq!(texts(&spans), vec!["x", "y", "z"]);
+        assert_eq!((spans[0].background, spans[0].style), (Some(*colors::NAVY), Style::BOLD));
+        assert_eq!((spans[1].background, spans[1].style), (Some(*colors::NAVY), Style::NONE));
+        assert_eq!((spans[2].background, spans[2].style), (None, Style::NONE));
+    }
+
+    #[test]
+    fn hex_colors_resolve() {
+        let spans = parse_markup("{#ff0000}x", &Palette::default());
+        assert_eq!(spans[0].foreground, Some(Color::from_int(255, 0, 0, 1.0)));
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        let spans = parse_markup("{{x}} and {{{white}y{/}}}", &Palette::default());
+        assert_eq!(texts(&spans), vec!["{x} and {", "y", "}"]);
+        assert_eq!(spans[1].foreground, Some(*colors::WHITE));
+    }
+
+    #[test]
+    fn lone_closing_braces_are_literal() {
+        let spans = parse_markup("a}b", &Palette::default());
+        assert_eq!(texts(&spans), vec!["a}b"]);
+    }
+
+    #[test]
+    fn unclosed_tags_are_kept_as_text() {
+        let spans = parse_markup("hp {red", &Palette::default());
+        assert_eq!(texts(&spans), vec!["hp {red"]);
+        assert_eq!(spans[0].foreground, None);
+    }
+
+    #[test]
+    fn tags_left_open_run_to_the_end() {
+        let spans = parse_markup("{u}open", &Palette::default());
+        assert_eq!(texts(&spans), vec!["open"]);
+        assert_eq!(spans[0].style, Style::UNDERLINE);
+    }
+
+    #[test]
+    fn unknown_tags_and_extra_pops_are_harmless() {
+        let spans = parse_markup("{nope}x{/}{/}y", &Palette::default());
+        assert_eq!(texts(&spans), vec!["{nope}x", "y"]);
+        assert!(spans.iter().all(|span| span.foreground.is_none() && span.style == Style::NONE));
+    }
+}
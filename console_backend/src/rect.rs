@@ -0,0 +1,49 @@
+// An area of cells, measured from the bottom left like console coordinates
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn with_dimensions(dimensions: (u32, u32)) -> Self {
+        Rect::new(0, 0, dimensions.0, dimensions.1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    // Last column inside the rect
+    pub fn right(&self) -> u32 {
+        self.x + self.width - 1
+    }
+
+    // Last row inside the rect
+    pub fn top(&self) -> u32 {
+        self.y + self.height - 1
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let top = (self.y + self.height).min(other.y + other.height);
+        Rect::new(x, y, right.saturating_sub(x), top.saturating_sub(y))
+    }
+}
@@ -71,6 +71,18 @@ impl<B> Buffer<B> where B: BufferType {
             )
         }
     }
+
+    // offset is counted in elements of T, not bytes
+    pub fn sub_data<T>(&self, offset: usize, data: &[T]) {
+        unsafe {
+            self.gl.BufferSubData(
+                B::BUFFER_TYPE,
+                (offset * ::std::mem::size_of::<T>()) as gl::types::GLintptr,
+                (data.len() * ::std::mem::size_of::<T>()) as gl::types::GLsizeiptr,
+                data.as_ptr() as *const gl::types::GLvoid,
+            )
+        }
+    }
 }
 
 impl<B> Drop for Buffer<B> where B: BufferType {
@@ -1,7 +1,7 @@
 use gl;
 use gl::Gl;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 #[repr(C, packed)]
 #[allow(non_camel_case_types)]
 pub struct f32_f32_f32_f32 {
@@ -39,7 +39,7 @@ impl f32_f32_f32_f32 {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 #[repr(C, packed)]
 #[allow(non_camel_case_types)]
 pub struct f32_f32_f32 {
@@ -76,7 +76,7 @@ impl f32_f32_f32 {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 #[repr(C, packed)]
 #[allow(non_camel_case_types)]
 pub struct f32_f32 {
@@ -0,0 +1,203 @@
+use core::ptr;
+use std::rc::Rc;
+use crate::backend::{RenderBackend, Placement};
+use crate::cell_buffer::{CellBuffer, Damage};
+use crate::console_vertex::{QuadVertex, GlyphInstance};
+use crate::font_info::FontInfo;
+use crate::glyph::Glyph;
+use crate::render_gl::Program;
+use crate::render_gl::buffer::{VertexArray, ArrayBuffer, ElementArrayBuffer};
+
+const QUAD_INDICES: [gl::types::GLuint; 6] = [0, 1, 3, 1, 2, 3];
+
+// Draws every cell as an instance of one unit quad
+pub struct GlyphRenderer {
+    gl: gl::Gl,
+    vao: VertexArray,
+    // Only referenced through the vao, kept so the buffers live as long as it does
+    #[allow(dead_code)]
+    quad: ArrayBuffer,
+    #[allow(dead_code)]
+    ebo: ElementArrayBuffer,
+    instance_buffer: ArrayBuffer,
+    program: Rc<Program>,
+    font_info: FontInfo,
+    // One instance per cell of every layer, laid out like the cell buffer. Empty cells keep
+    // their slot with an instance that draws nothing, so the buffer only changes size along
+    // with the console.
+    instances: Vec<GlyphInstance>,
+    // Font generation the instances were built against
+    font_generation: u32,
+}
+
+impl GlyphRenderer {
+    // program is shaders/glyph, or glyph_sdf for distance field fonts
+    pub fn new(gl: &gl::Gl, program: Rc<Program>, font_info: FontInfo) -> Self {
+        let vao = VertexArray::new(gl);
+        let quad = ArrayBuffer::new(gl);
+        let instance_buffer = ArrayBuffer::new(gl);
+        let ebo = ElementArrayBuffer::new(gl);
+
+        let corners: Vec<QuadVertex> = vec![
+            QuadVertex { corner: (1.0, 1.0).into() },
+            QuadVertex { corner: (1.0, 0.0).into() },
+            QuadVertex { corner: (0.0, 0.0).into() },
+            QuadVertex { corner: (0.0, 1.0).into() },
+        ];
+
+        vao.bind();
+        quad.bind();
+        quad.static_draw_data(&corners);
+        QuadVertex::vertex_attrib_pointers(gl);
+        instance_buffer.bind();
+        GlyphInstance::vertex_attrib_pointers(gl);
+        ebo.bind();
+        ebo.static_draw_data(&QUAD_INDICES);
+        vao.unbind();
+        instance_buffer.unbind();
+        ebo.unbind();
+
+        GlyphRenderer {
+            gl: gl.clone(),
+            vao,
+            quad,
+            ebo,
+            instance_buffer,
+            program,
+            instances: vec![],
+            font_generation: font_info.generation(),
+            font_info,
+        }
+    }
+
+    fn glyph_instance(&self, dimensions: (u32, u32), position: (u32, u32), layer: u32, glyph: &Glyph) -> GlyphInstance {
+        let bounding_box = self.font_info.bounding_box(glyph.character);
+        let bottom_left = bounding_box.bottom_left(self.font_info.texture_scale);
+        let top_right = bounding_box.top_right(self.font_info.texture_scale);
+        let (cell_min, cell_max) = (bounding_box.cell_min(), bounding_box.cell_max());
+        let (background, foreground) = glyph.display_colors();
+        GlyphInstance {
+            cell: ((position.0 + position.1 * dimensions.0) as f32, layer as f32, glyph.style.bits() as f32).into(),
+            texture: (bottom_left.0, bottom_left.1, top_right.0, top_right.1).into(),
+            background,
+            foreground,
+            glyph_quad: (cell_min.0, cell_min.1, cell_max.0, cell_max.1).into(),
+        }
+    }
+
+    fn cell_instance(&self, cells: &CellBuffer, position: (u32, u32), layer: u32) -> GlyphInstance {
+        let dimensions = cells.dimensions();
+        match cells.get_cell(position.0, position.1, layer) {
+            Some(glyph) => self.glyph_instance(dimensions, position, layer, glyph),
+            None => GlyphInstance::empty(cells.coordinates_to_index(position.0, position.1) as f32, layer as f32),
+        }
+    }
+
+    // Rewrites every instance, only reallocating the buffer when the console changed size
+    fn rebuild(&mut self, cells: &CellBuffer) {
+        let dimensions = cells.dimensions();
+        let mut instances = Vec::with_capacity(slot_count(cells));
+        for layer in 0..cells.layer_count() {
+            for y in 0..dimensions.1 {
+                for x in 0..dimensions.0 {
+                    instances.push(self.cell_instance(cells, (x, y), layer));
+                }
+            }
+        }
+        self.instance_buffer.bind();
+        if instances.len() == self.instances.len() {
+            self.instance_buffer.sub_data(0, &instances);
+        } else {
+            self.instance_buffer.dynamic_draw_data(&instances);
+        }
+        self.instance_buffer.unbind();
+        self.instances = instances;
+    }
+
+    // Rewrites the instances of the damaged cells and uploads the rows they cover, layer by layer
+    fn update_region(&mut self, cells: &CellBuffer, min: (u32, u32), max: (u32, u32)) {
+        let layer_size = (cells.dimensions().0 * cells.dimensions().1) as usize;
+        let (first, last) = (cells.coordinates_to_index(min.0, min.1), cells.coordinates_to_index(max.0, max.1));
+        self.instance_buffer.bind();
+        for layer in 0..cells.layer_count() {
+            let offset = layer as usize * layer_size;
+            for y in min.1..=max.1 {
+                for x in min.0..=max.0 {
+                    self.instances[offset + cells.coordinates_to_index(x, y)] = self.cell_instance(cells, (x, y), layer);
+                }
+            }
+            self.instance_buffer.sub_data(offset + first, &self.instances[offset + first..=offset + last]);
+        }
+        self.instance_buffer.unbind();
+    }
+
+    fn cache_damaged_glyphs(&self, cells: &CellBuffer) {
+        let damage = cells.damage();
+        if let Damage::Clean = damage {
+            return;
+        }
+        let chars = cells.iter()
+            .filter(|(position, _, _)| damage.contains(*position))
+            .map(|(_, _, glyph)| glyph.character);
+        self.font_info.cache_glyphs(chars);
+    }
+
+    fn set_uniforms(&self, dimensions: (u32, u32), placement: &Placement) {
+        self.program.set_vec_2f("dimensions", (dimensions.0 as f32, dimensions.1 as f32));
+        self.program.set_vec_2f("screenScale", (
+            placement.screen_scaling.0 * placement.scale_modifier.0,
+            placement.screen_scaling.1 * placement.scale_modifier.1,
+        ));
+        self.program.set_vec_2f("screenOffset", placement.screen_offset);
+        self.program.set_float("depth", placement.height as f32);
+    }
+}
+
+impl RenderBackend for GlyphRenderer {
+    fn render(&mut self, cells: &CellBuffer, placement: &Placement) {
+        unsafe {
+            self.gl.Enable(gl::DEPTH_TEST);
+        }
+        self.cache_damaged_glyphs(cells);
+        let font_generation = self.font_info.generation();
+        match cells.damage() {
+            _ if font_generation != self.font_generation || self.instances.len() != slot_count(cells) => self.rebuild(cells),
+            Damage::Clean => {},
+            Damage::Region { min, max } => self.update_region(cells, min, max),
+            Damage::Full => self.rebuild(cells),
+        }
+        self.font_generation = font_generation;
+        if self.instances.is_empty() {
+            return;
+        }
+        self.program.set_used();
+        self.set_uniforms(cells.dimensions(), placement);
+
+        self.font_info.texture.bind();
+
+        self.vao.bind();
+        unsafe {
+            self.gl.DrawElementsInstanced(
+                gl::TRIANGLES,
+                QUAD_INDICES.len() as i32,
+                gl::UNSIGNED_INT,
+                ptr::null(),
+                self.instances.len() as i32,
+            );
+        }
+        self.vao.unbind();
+    }
+
+    fn font_info(&self) -> Option<&FontInfo> {
+        Some(&self.font_info)
+    }
+
+    fn font_info_mut(&mut self) -> Option<&mut FontInfo> {
+        Some(&mut self.font_info)
+    }
+}
+
+// Instances a buffer of this size and layer count takes
+fn slot_count(cells: &CellBuffer) -> usize {
+    (cells.dimensions().0 * cells.dimensions().1 * cells.layer_count()) as usize
+}
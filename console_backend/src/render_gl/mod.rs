@@ -7,4 +7,5 @@ pub mod buffer;
 pub mod viewport;
 pub mod color_buffer;
 pub mod texture;
-pub mod errors;
\ No newline at end of file
+pub mod errors;
+pub mod glyph_renderer;
\ No newline at end of file
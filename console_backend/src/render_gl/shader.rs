@@ -16,10 +16,17 @@ impl Program {
             ".frag",
         ];
 
-        let shaders = POSSIBLE_EXT.iter()
-            .map(|file_extension| {
-                Shader::from_res(gl, res, &format!("{}{}", name, file_extension))
-            })
+        let files: Vec<String> = POSSIBLE_EXT.iter()
+            .map(|file_extension| format!("{}{}", name, file_extension))
+            .collect();
+        let files: Vec<&str> = files.iter().map(|file| file.as_str()).collect();
+        Program::from_res_files(gl, res, name, &files)
+    }
+
+    // For programs sharing a shader, like shaders/glyph.vert with both glyph fragment shaders
+    pub fn from_res_files(gl: &Gl, res: &Resources, name: &str, files: &[&str]) -> Result<Self, Error> {
+        let shaders = files.iter()
+            .map(|file| Shader::from_res(gl, res, file))
             .collect::<Result<Vec<Shader>, Error>>()?;
 
         Program::from_shaders(gl, &shaders).map_err(|message| Error::LinkError {
@@ -100,6 +107,12 @@ impl Program {
         unsafe { self.gl.Uniform1f(location, value as gl::types::GLfloat) }
     }
 
+    #[allow(dead_code)]
+    pub fn set_vec_2f(&self, name: &str, value: (f32, f32)) {
+        let location = self.get_uniform_location(name);
+        unsafe { self.gl.Uniform2f(location, value.0 as gl::types::GLfloat, value.1 as gl::types::GLfloat) }
+    }
+
     #[allow(dead_code)]
     pub fn set_mat_4f(&self, name: &str, value: nalgebra_glm::Mat4) {
         let location = self.get_uniform_location(name);
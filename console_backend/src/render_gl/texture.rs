@@ -4,7 +4,7 @@ use crate::render_gl::errors::Error;
 use gl::Gl;
 use failure::_core::ffi::c_void;
 
-#[derive(Clone)]
+// Owns the GL texture name, share it behind an Rc
 pub struct Texture {
     texture:  gl::types::GLuint,
     gl: Gl,
@@ -54,9 +54,79 @@ impl Texture {
         })
     }
 
+    // Transparent texture to be filled in later with update
+    pub fn new_empty(gl: &Gl, dimensions: (u32, u32)) -> Self {
+        let mut texture = 0;
+        unsafe {
+            gl.GenTextures(1, &mut texture);
+            gl.BindTexture(gl::TEXTURE_2D, texture);
+
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER as i32);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER as i32);
+
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+
+            let pixels = vec![0u8; (dimensions.0 * dimensions.1 * 4) as usize];
+            gl.TexImage2D(gl::TEXTURE_2D,
+                          0,
+                          gl::RGBA as i32,
+                          dimensions.0 as i32,
+                          dimensions.1 as i32,
+                          0,
+                          gl::RGBA,
+                          gl::UNSIGNED_BYTE,
+                          pixels.as_ptr() as *const c_void);
+        }
+        Texture {
+            texture,
+            gl: gl.clone(),
+        }
+    }
+
+    // Replaces a region with RGBA pixels, rows counted from the bottom
+    pub fn update(&self, offset: (u32, u32), dimensions: (u32, u32), pixels: &[u8]) {
+        unsafe {
+            self.gl.BindTexture(gl::TEXTURE_2D, self.texture);
+            self.gl.TexSubImage2D(gl::TEXTURE_2D,
+                                  0,
+                                  offset.0 as i32,
+                                  offset.1 as i32,
+                                  dimensions.0 as i32,
+                                  dimensions.1 as i32,
+                                  gl::RGBA,
+                                  gl::UNSIGNED_BYTE,
+                                  pixels.as_ptr() as *const c_void);
+        }
+    }
+
+    // Nearest keeps pixel art crisp when it's scaled up
+    pub fn set_mag_filter(&self, filter: gl::types::GLenum) {
+        unsafe {
+            self.gl.BindTexture(gl::TEXTURE_2D, self.texture);
+            self.gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter as i32);
+        }
+    }
+
+    // Distance field atlases need linear sampling when shrunk too
+    pub fn set_min_filter(&self, filter: gl::types::GLenum) {
+        unsafe {
+            self.gl.BindTexture(gl::TEXTURE_2D, self.texture);
+            self.gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter as i32);
+        }
+    }
+
     pub fn bind(&self) {
         unsafe {
             self.gl.BindTexture(gl::TEXTURE_2D, self.texture);
         }
     }
-}
\ No newline at end of file
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteTextures(1, &self.texture);
+        }
+    }
+}
@@ -2,6 +2,8 @@ use std::path::{PathBuf, Path};
 use std::{io, ffi, fs};
 use std::io::Read;
 use image::{ImageError, DynamicImage};
+use crate::cell_buffer::CellBuffer;
+use crate::rexpaint::{read_xp, XpError};
 
 #[derive(Debug, Fail)]
 pub enum Error {
@@ -9,6 +11,8 @@ pub enum Error {
     Io(#[cause] io::Error),
     #[fail(display = "Image error")]
     FailedToLoadImage(#[cause] ImageError),
+    #[fail(display = "REXPaint error")]
+    FailedToLoadXp(#[cause] XpError),
     #[fail(display = "Failed to read CString from file that contains 0")]
     FileContainsNil,
     #[fail(display = "Failed to get executable path")]
@@ -27,6 +31,13 @@ impl From<ImageError> for Error {
     }
 }
 
+impl From<XpError> for Error {
+    fn from(other: XpError) -> Self {
+        Error::FailedToLoadXp(other)
+    }
+}
+
+#[derive(Clone)]
 pub struct Resources {
     root_path: PathBuf,
 }
@@ -55,6 +66,14 @@ impl Resources {
             resource_name_to_path(&self.root_path, resource_name))?)
     }
 
+    // A gzip'd REXPaint image, see read_xp
+    pub fn load_xp(&self, resource_name: &str) -> Result<CellBuffer, Error> {
+        let file = fs::File::open(
+            resource_name_to_path(&self.root_path, resource_name)
+        )?;
+        Ok(read_xp(io::BufReader::new(file))?)
+    }
+
     pub fn load_bytes_from_file(&self, resource_name: &str) -> Result<Vec<u8>, Error> {
         let mut file = fs::File::open(
             resource_name_to_path(&self.root_path, resource_name)
@@ -0,0 +1,237 @@
+use std::io::{self, Read, Write};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use font_renderer::{cp437_to_char, char_to_cp437};
+use crate::cell_buffer::CellBuffer;
+use crate::style::Style;
+use crate::color::colors;
+use crate::Color;
+
+// Written by REXPaint 1.0 and later
+const XP_VERSION: i32 = -1;
+// Backgrounds of this color mark transparent cells
+const TRANSPARENT: (u8, u8, u8) = (255, 0, 255);
+// Stands in for characters CP437 doesn't have
+const UNKNOWN: u8 = b'?';
+
+#[derive(Debug, Fail)]
+pub enum XpError {
+    #[fail(display = "I/O error")]
+    Io(#[cause] io::Error),
+    #[fail(display = "Invalid layer count {}", _0)]
+    InvalidLayerCount(i32),
+    #[fail(display = "Invalid layer size {}x{}", _0, _1)]
+    InvalidLayerSize(i32, i32),
+}
+
+impl From<io::Error> for XpError {
+    fn from(other: io::Error) -> Self {
+        XpError::Io(other)
+    }
+}
+
+// Reads a gzip'd REXPaint image, .xp layer n becoming layer n of the buffer. Cells with the
+// transparent magenta background are left empty whatever their character, like REXPaint
+// shows them, so the layers below show through.
+pub fn read_xp<R: Read>(reader: R) -> Result<CellBuffer, XpError> {
+    let mut data = vec![];
+    GzDecoder::new(reader).read_to_end(&mut data)?;
+    let mut input = &data[..];
+
+    let _version = read_i32(&mut input)?;
+    let layer_count = read_i32(&mut input)?;
+    if layer_count <= 0 || layer_count > 256 {
+        return Err(XpError::InvalidLayerCount(layer_count));
+    }
+
+    let mut layers = Vec::with_capacity(layer_count as usize);
+    for _ in 0..layer_count {
+        let (width, height) = (read_i32(&mut input)?, read_i32(&mut input)?);
+        if width <= 0 || height <= 0 || width.checked_mul(height).map_or(true, |cells| cells as usize * 10 > input.len()) {
+            return Err(XpError::InvalidLayerSize(width, height));
+        }
+        let mut cells = Vec::with_capacity((width * height) as usize);
+        for _ in 0..width * height {
+            let code = read_i32(&mut input)?;
+            let mut colors = [0; 6];
+            input.read_exact(&mut colors)?;
+            cells.push((code, (colors[0], colors[1], colors[2]), (colors[3], colors[4], colors[5])));
+        }
+        layers.push(((width as u32, height as u32), cells));
+    }
+
+    // Layers should all be the same size, the buffer fits the biggest one anyway
+    let dimensions = layers.iter().fold((0, 0), |size, ((width, height), _)| (size.0.max(*width), size.1.max(*height)));
+    let mut buffer = CellBuffer::with_layers(dimensions, layer_count as u32, *colors::BLACK);
+    for (layer, ((_, height), cells)) in layers.iter().enumerate() {
+        // Stored column by column from the top left, consoles count rows from the bottom
+        for (index, &(code, foreground, background)) in cells.iter().enumerate() {
+            let (x, row) = (index as u32 / height, index as u32 % height);
+            let y = (dimensions.1 - 1 - row) as i32;
+            let c = if code >= 0 && code < 256 { cp437_to_char(code as u8) } else { UNKNOWN as char };
+            if background == TRANSPARENT {
+                continue;
+            }
+            buffer.put_char_styled(c, x as i32, y, to_color(foreground), Some(to_color(background)), Style::NONE, layer as u32);
+        }
+    }
+    Ok(buffer)
+}
+
+// Writes every layer of the buffer as a gzip'd REXPaint image. Empty cells and blank ones with
+// a clear background become transparent magenta, so read_xp drops them. Characters over a
+// clear background get the background the layers below show there. Styles are lost.
+// The format only has CP437, any other character is written as '?' without an error, check
+// with char_to_cp437 first if that matters.
+pub fn write_xp<W: Write>(cells: &CellBuffer, writer: W) -> Result<(), XpError> {
+    let (width, height) = cells.dimensions();
+    let mut data = vec![];
+    data.extend_from_slice(&XP_VERSION.to_le_bytes());
+    data.extend_from_slice(&(cells.layer_count() as i32).to_le_bytes());
+    for layer in 0..cells.layer_count() {
+        data.extend_from_slice(&(width as i32).to_le_bytes());
+        data.extend_from_slice(&(height as i32).to_le_bytes());
+        for x in 0..width {
+            for y in (0..height).rev() {
+                let (code, foreground, background) = match cells.get_cell(x, y, layer) {
+                    Some(glyph) if glyph.background.a < 0.1 && glyph.character == ' ' => (0, (0, 0, 0), TRANSPARENT),
+                    Some(glyph) => {
+                        let code = char_to_cp437(glyph.character).unwrap_or(UNKNOWN);
+                        let background = if glyph.background.a < 0.1 {
+                            cells.background_below(x, y, layer)
+                        } else {
+                            glyph.background
+                        };
+                        (code, to_rgb(glyph.foreground), to_rgb(background))
+                    },
+                    None => (0, (0, 0, 0), TRANSPARENT),
+                };
+                data.extend_from_slice(&(code as i32).to_le_bytes());
+                data.extend_from_slice(&[foreground.0, foreground.1, foreground.2, background.0, background.1, background.2]);
+            }
+        }
+    }
+    let mut encoder = GzEncoder::new(writer, Compression::default());
+    encoder.write_all(&data)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+// The topmost opaque background under layer, the buffer's default if there's none
+fn read_i32(input: &mut &[u8]) -> io::Result<i32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(i32::from_le_bytes(bytes))
+}
+
+fn to_color((r, g, b): (u8, u8, u8)) -> Color {
+    Color::from_int(r as i32, g as i32, b as i32, 1.0)
+}
+
+fn to_rgb(color: Color) -> (u8, u8, u8) {
+    let channel = |value: f32| (value.max(0.0).min(1.0) * 255.0).round() as u8;
+    (channel(color.x), channel(color.y), channel(color.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    // A one layer image, cells given column by column from the top like the format stores them
+    fn xp(dimensions: (i32, i32), cells: &[(i32, [u8; 6])]) -> Vec<u8> {
+        let mut data = vec![];
+        for value in &[XP_VERSION, 1, dimensions.0, dimensions.1] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        for (code, colors) in cells {
+            data.extend_from_slice(&code.to_le_bytes());
+            data.extend_from_slice(colors);
+        }
+        data
+    }
+
+    #[test]
+    fn round_trips_layers_glyphs_and_colors() {
+        let mut cells = CellBuffer::with_layers((3, 2), 2, *colors::BLACK);
+        cells.put_text("a☺", 0, 1, Color::from_int(200, 10, 10, 1.0), Some(Color::from_int(0, 0, 100, 1.0)), 0);
+        cells.put_char('█', 2, 0, *colors::WHITE, Some(*colors::BLACK), 0);
+        cells.put_char('@', 1, 0, *colors::WHITE, Some(Color::from_int(20, 120, 20, 1.0)), 1);
+
+        let mut file = vec![];
+        write_xp(&cells, &mut file).unwrap();
+        let read = read_xp(&file[..]).unwrap();
+
+        assert_eq!(read.dimensions(), (3, 2));
+        assert_eq!(read.layer_count(), 2);
+        assert_eq!(read.snapshot(), cells.snapshot());
+    }
+
+    #[test]
+    fn glyphs_over_clear_backgrounds_keep_what_is_below() {
+        let mut cells = CellBuffer::with_layers((1, 1), 2, *colors::BLACK);
+        cells.put_char(' ', 0, 0, *colors::WHITE, Some(*colors::NAVY), 0);
+        cells.put_char('o', 0, 0, *colors::WHITE, Some(*colors::CLEAR), 1);
+
+        let mut file = vec![];
+        write_xp(&cells, &mut file).unwrap();
+        let read = read_xp(&file[..]).unwrap();
+
+        let glyph = read.get_cell(0, 0, 1).unwrap();
+        assert_eq!((glyph.character, glyph.background), ('o', *colors::NAVY));
+    }
+
+    #[test]
+    fn characters_outside_cp437_are_written_as_question_marks() {
+        let mut cells = CellBuffer::with_layers((1, 1), 1, *colors::BLACK);
+        cells.put_char('λ', 0, 0, *colors::WHITE, Some(*colors::BLACK), 0);
+        let mut file = vec![];
+        write_xp(&cells, &mut file).unwrap();
+        assert_eq!(read_xp(&file[..]).unwrap().row_text(0), "?");
+    }
+
+    #[test]
+    fn magenta_cells_are_empty() {
+        let file = gzip(&xp((2, 1), &[(b'#' as i32, [255, 255, 255, 255, 0, 255]), (b'#' as i32, [255, 255, 255, 0, 0, 0])]));
+        let read = read_xp(&file[..]).unwrap();
+        assert!(read.get_cell(0, 0, 0).is_none());
+        assert_eq!(read.get_cell(1, 0, 0).map(|glyph| glyph.character), Some('#'));
+    }
+
+    #[test]
+    fn rows_are_flipped_to_count_from_the_bottom() {
+        let file = gzip(&xp((1, 2), &[(b'T' as i32, [255; 6]), (b'B' as i32, [255; 6])]));
+        let read = read_xp(&file[..]).unwrap();
+        assert_eq!(read.snapshot().to_string(), "T\nB");
+    }
+
+    #[test]
+    fn truncated_files_are_an_error() {
+        let whole = xp((2, 2), &[(b'x' as i32, [0; 6]); 4]);
+        for length in &[0, 6, 12, whole.len() - 1] {
+            assert!(read_xp(&gzip(&whole[..*length])[..]).is_err(), "{} bytes", length);
+        }
+        let file = gzip(&whole);
+        assert!(read_xp(&file[..file.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn garbage_is_an_error() {
+        assert!(read_xp(&b"not gzip at all"[..]).is_err());
+        match read_xp(&gzip(&xp((-3, 2), &[]))[..]) {
+            Err(XpError::InvalidLayerSize(-3, 2)) => {},
+            other => panic!("{:?}", other.map(|cells| cells.dimensions())),
+        }
+        let mut no_layers = xp((1, 1), &[]);
+        no_layers[4..8].copy_from_slice(&0i32.to_le_bytes());
+        assert!(read_xp(&gzip(&no_layers)[..]).is_err());
+        // Claims far more cells than there is data for
+        assert!(read_xp(&gzip(&xp((100_000, 100_000), &[]))[..]).is_err());
+    }
+}
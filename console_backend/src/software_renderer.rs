@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use image::{DynamicImage, Rgba, RgbaImage};
+use font_renderer::{load_bitmap_with_charset, load_sdf_with_charset, load_tileset, BoundingBox, Charset, TilesetLayout};
+use crate::resources::Resources;
+use crate::cell_buffer::CellBuffer;
+use crate::glyph::Glyph;
+use crate::style::Style;
+use crate::font_info::{find_glyph, FontError, DEFAULT_REPLACEMENT};
+use crate::Color;
+
+// Named in errors about fonts handed over as bytes
+const IN_MEMORY_FONT: &str = "<font bytes>";
+
+// CPU version of the glyph shader, used to produce console images without a GL context
+pub struct SoftwareRenderer {
+    atlas: RgbaImage,
+    glyph_map: HashMap<char, BoundingBox>,
+    cell_size: (u32, u32),
+    clear_color: Rgba<u8>,
+    replacement: char,
+    // Distance field atlas, cut at the outline instead of shaded smoothly
+    sdf: bool,
+}
+
+impl SoftwareRenderer {
+    pub fn new(atlas: &DynamicImage, glyph_map: HashMap<char, BoundingBox>, cell_size: (u32, u32)) -> Self {
+        SoftwareRenderer {
+            atlas: atlas.to_rgba(),
+            glyph_map,
+            cell_size,
+            clear_color: Rgba([0, 0, 0, 0]),
+            replacement: DEFAULT_REPLACEMENT,
+            sdf: false,
+        }
+    }
+
+    pub fn from_font(font_bytes: Vec<u8>, cell_size: (u32, u32)) -> Result<Self, failure::Error> {
+        SoftwareRenderer::from_font_with_charset(font_bytes, &Charset::default(), cell_size)
+    }
+
+    pub fn from_font_with_charset(font_bytes: Vec<u8>, charset: &Charset, cell_size: (u32, u32)) -> Result<Self, failure::Error> {
+        let (atlas, glyph_map) = load_bitmap_with_charset(font_bytes, charset)
+            .ok_or_else(|| FontError::InvalidFont { name: IN_MEMORY_FONT.into() })?;
+        Ok(SoftwareRenderer::new(&atlas, glyph_map, cell_size))
+    }
+
+    pub fn from_font_sdf(font_bytes: Vec<u8>, charset: &Charset, cell_size: (u32, u32)) -> Result<Self, failure::Error> {
+        let (atlas, glyph_map) = load_sdf_with_charset(font_bytes, charset)
+            .ok_or_else(|| FontError::InvalidFont { name: IN_MEMORY_FONT.into() })?;
+        let mut renderer = SoftwareRenderer::new(&atlas, glyph_map, cell_size);
+        renderer.sdf = true;
+        Ok(renderer)
+    }
+
+    pub fn from_tileset(tileset: &DynamicImage, layout: TilesetLayout, codepage: &[char], cell_size: (u32, u32)) -> Result<Self, failure::Error> {
+        let (atlas, glyph_map) = load_tileset(tileset, layout, codepage)?;
+        Ok(SoftwareRenderer::new(&atlas, glyph_map, cell_size))
+    }
+
+    pub fn from_res(res: &Resources, font: &str, cell_size: (u32, u32)) -> Result<Self, failure::Error> {
+        let font_bytes = res.load_bytes_from_file(font)
+            .map_err(|inner| FontError::ResourceLoad { name: font.into(), inner })?;
+        let (atlas, glyph_map) = load_bitmap_with_charset(font_bytes, &Charset::default())
+            .ok_or_else(|| FontError::InvalidFont { name: font.into() })?;
+        Ok(SoftwareRenderer::new(&atlas, glyph_map, cell_size))
+    }
+
+    pub fn with_clear_color(mut self, color: Color) -> Self {
+        self.clear_color = to_rgba(color);
+        self
+    }
+
+    pub fn with_replacement_glyph(mut self, replacement: char) -> Self {
+        self.replacement = replacement;
+        self
+    }
+
+    pub fn can_render(&self, c: char) -> bool {
+        self.glyph_map.contains_key(&c)
+    }
+
+    pub fn cell_size(&self) -> (u32, u32) {
+        self.cell_size
+    }
+
+    pub fn render(&self, cells: &CellBuffer) -> RgbaImage {
+        self.render_cells(cells.dimensions(), cells.iter())
+    }
+
+    // cells are ((x, y), layer, glyph) with y = 0 being the bottom row, as in Console::put_char
+    pub fn render_cells<'a, I>(&self, dimensions: (u32, u32), cells: I) -> RgbaImage
+        where I: IntoIterator<Item = ((u32, u32), u32, &'a Glyph)> {
+        let (cell_width, cell_height) = self.cell_size;
+        let mut image = RgbaImage::from_pixel(
+            dimensions.0 * cell_width,
+            dimensions.1 * cell_height,
+            self.clear_color,
+        );
+
+        let mut cells: Vec<_> = cells.into_iter()
+            .filter(|((x, y), _, _)| *x < dimensions.0 && *y < dimensions.1)
+            .collect();
+        // Later draws win, so lower layers have to be painted first
+        cells.sort_by_key(|(_, layer, _)| *layer);
+
+        for ((x, y), _, glyph) in cells {
+            let origin = (x * cell_width, (dimensions.1 - 1 - y) * cell_height);
+            let bounding_box = find_glyph(&self.glyph_map, glyph.character, self.replacement);
+            for row in 0..cell_height {
+                for column in 0..cell_width {
+                    let texel = match bounding_box {
+                        Some(bounding_box) => self.sample(bounding_box, glyph.style, column, row),
+                        None => Rgba([0, 0, 0, 0]),
+                    };
+                    if let Some(pixel) = shade(texel, glyph) {
+                        image.put_pixel(origin.0 + column, origin.1 + row, pixel);
+                    }
+                }
+            }
+        }
+        image
+    }
+
+    // Texel for a pixel inside a cell, row 0 being the top of the cell, with the style synthesized
+    // like the shaders do
+    fn sample(&self, bounding_box: &BoundingBox, style: Style, column: u32, row: u32) -> Rgba<u8> {
+        let cell_y = 1.0 - (row as f32 + 0.5) / self.cell_size.1 as f32;
+        let lined = (style.contains(Style::UNDERLINE) && cell_y >= 0.06 && cell_y < 0.12)
+            || (style.contains(Style::STRIKETHROUGH) && cell_y >= 0.42 && cell_y < 0.48);
+        if lined {
+            return Rgba([255, 255, 255, 255]);
+        }
+        let mut cell_x = (column as f32 + 0.5) / self.cell_size.0 as f32;
+        if style.contains(Style::ITALIC) {
+            cell_x -= (cell_y - 0.25) * 0.2;
+        }
+
+        let texel = self.texel(bounding_box, cell_x, cell_y);
+        if self.sdf {
+            // Bold moves the outline outwards
+            let edge = if style.contains(Style::BOLD) { 107 } else { 128 };
+            let alpha = if texel[3] >= edge { 255 } else { 0 };
+            return Rgba([texel[0], texel[1], texel[2], alpha]);
+        }
+        if style.contains(Style::BOLD) {
+            let shifted = self.texel(bounding_box, cell_x + 0.06, cell_y);
+            if shifted[3] > texel[3] {
+                return shifted;
+            }
+        }
+        texel
+    }
+
+    // Nearest texel at a point of the cell measured from its bottom left, transparent outside the
+    // glyph's quad
+    fn texel(&self, bounding_box: &BoundingBox, cell_x: f32, cell_y: f32) -> Rgba<u8> {
+        let (x1, y1) = bounding_box.min();
+        let (x2, y2) = bounding_box.max();
+        let (cell_min, cell_max) = (bounding_box.cell_min(), bounding_box.cell_max());
+        let glyph_x = (cell_x - cell_min.0) / (cell_max.0 - cell_min.0);
+        let glyph_y = (cell_y - cell_min.1) / (cell_max.1 - cell_min.1);
+        if glyph_x < 0.0 || glyph_y < 0.0 || glyph_x >= 1.0 || glyph_y >= 1.0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let s = x1 as f32 + glyph_x * (x2 - x1) as f32;
+        let t = y1 as f32 + glyph_y * (y2 - y1) as f32;
+        let (width, height) = self.atlas.dimensions();
+        if s < 0.0 || t < 0.0 || s >= width as f32 || t >= height as f32 {
+            // Matches CLAMP_TO_BORDER with the default transparent border
+            return Rgba([0, 0, 0, 0]);
+        }
+        // The GL texture is flipped on upload, so t counts rows from the bottom of the atlas
+        *self.atlas.get_pixel(s as u32, height - 1 - t as u32)
+    }
+}
+
+// Mirrors glyph.frag; None means the fragment is discarded
+fn shade(texel: Rgba<u8>, glyph: &Glyph) -> Option<Rgba<u8>> {
+    let texel_alpha = texel[3] as f32 / 255.0;
+    let (background, foreground) = glyph.display_colors();
+    if texel_alpha < 0.1 {
+        if background.a < 0.1 {
+            None
+        } else {
+            Some(to_rgba(background))
+        }
+    } else if foreground.a < 0.1 {
+        Some(texel)
+    } else {
+        Some(Rgba([
+            channel(texel[0] as f32 / 255.0 * foreground.x),
+            channel(texel[1] as f32 / 255.0 * foreground.y),
+            channel(texel[2] as f32 / 255.0 * foreground.z),
+            channel(texel_alpha * foreground.a),
+        ]))
+    }
+}
+
+fn to_rgba(color: Color) -> Rgba<u8> {
+    Rgba([channel(color.x), channel(color.y), channel(color.z), channel(color.a)])
+}
+
+fn channel(value: f32) -> u8 {
+    (value.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: (u8, u8, u8) = (255, 0, 0);
+    const GREEN: (u8, u8, u8) = (0, 255, 0);
+    const BLUE: (u8, u8, u8) = (0, 0, 255);
+
+    fn color((r, g, b): (u8, u8, u8)) -> Color {
+        Color::from_int(r as i32, g as i32, b as i32, 1.0)
+    }
+
+    fn pixel((r, g, b): (u8, u8, u8)) -> Rgba<u8> {
+        Rgba([r, g, b, 255])
+    }
+
+    // Two 2x2 tiles, ' ' is empty and '#' only has its top row set
+    fn renderer() -> SoftwareRenderer {
+        let mut sheet = RgbaImage::from_pixel(4, 2, Rgba([255, 255, 255, 0]));
+        sheet.put_pixel(2, 0, Rgba([255, 255, 255, 255]));
+        sheet.put_pixel(3, 0, Rgba([255, 255, 255, 255]));
+        SoftwareRenderer::from_tileset(&DynamicImage::ImageRgba8(sheet), TilesetLayout::grid(2, 1), &[' ', '#'], (2, 2)).unwrap()
+    }
+
+    #[test]
+    fn glyphs_take_the_foreground_and_the_rest_of_the_cell_the_background() {
+        let mut cells = CellBuffer::new((2, 1), color((0, 0, 0)));
+        cells.put_char('#', 0, 0, color(RED), Some(color(BLUE)), 1);
+        cells.put_char(' ', 1, 0, color(RED), Some(color(GREEN)), 1);
+        let image = renderer().render(&cells);
+
+        assert_eq!(image.dimensions(), (4, 2));
+        assert_eq!(*image.get_pixel(0, 0), pixel(RED));
+        assert_eq!(*image.get_pixel(1, 1), pixel(BLUE));
+        assert_eq!(*image.get_pixel(2, 0), pixel(GREEN));
+        assert_eq!(*image.get_pixel(3, 1), pixel(GREEN));
+    }
+
+    #[test]
+    fn higher_layers_are_drawn_over_lower_ones() {
+        let mut cells = CellBuffer::new((2, 1), color((0, 0, 0)));
+        // A glyph over a clear background lets the layer below show around it
+        cells.put_char(' ', 0, 0, color(RED), Some(color(BLUE)), 1);
+        cells.put_char('#', 0, 0, color(RED), Some(*crate::colors::CLEAR), 2);
+        // An opaque background hides the glyph below it
+        cells.put_char('#', 1, 0, color(RED), Some(color(BLUE)), 1);
+        cells.put_char(' ', 1, 0, color(RED), Some(color(GREEN)), 2);
+        let image = renderer().render(&cells);
+
+        assert_eq!(*image.get_pixel(0, 0), pixel(RED));
+        assert_eq!(*image.get_pixel(0, 1), pixel(BLUE));
+        assert_eq!(*image.get_pixel(2, 0), pixel(GREEN));
+        assert_eq!(*image.get_pixel(3, 1), pixel(GREEN));
+    }
+
+    #[test]
+    fn rows_are_counted_from_the_bottom() {
+        let mut cells = CellBuffer::new((1, 2), color((0, 0, 0)));
+        cells.put_char(' ', 0, 0, color(RED), Some(color(GREEN)), 1);
+        let image = renderer().with_clear_color(color(BLUE)).render(&cells);
+
+        assert_eq!(*image.get_pixel(0, 0), pixel(BLUE));
+        assert_eq!(*image.get_pixel(0, 3), pixel(GREEN));
+    }
+
+    #[test]
+    fn bad_fonts_are_an_error() {
+        assert!(SoftwareRenderer::from_font(vec![1, 2, 3], (8, 8)).is_err());
+        let sheet = DynamicImage::ImageRgba8(RgbaImage::new(4, 2));
+        assert!(SoftwareRenderer::from_tileset(&sheet, TilesetLayout::grid(0, 1), &[' '], (2, 2)).is_err());
+    }
+}
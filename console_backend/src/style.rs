@@ -0,0 +1,44 @@
+use std::ops::{BitOr, BitOrAssign};
+
+// Text attributes of a cell, combined with |. All of them are synthesized by the renderers
+// from the regular font.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Style(u8);
+
+impl Style {
+    pub const NONE: Style = Style(0);
+    pub const BOLD: Style = Style(1);
+    pub const ITALIC: Style = Style(1 << 1);
+    pub const UNDERLINE: Style = Style(1 << 2);
+    pub const STRIKETHROUGH: Style = Style(1 << 3);
+    // Swaps foreground and background
+    pub const REVERSE: Style = Style(1 << 4);
+    // Halves the foreground brightness
+    pub const DIM: Style = Style(1 << 5);
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(self, other: Style) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for Style {
+    type Output = Style;
+
+    fn bitor(self, other: Style) -> Style {
+        Style(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for Style {
+    fn bitor_assign(&mut self, other: Style) {
+        self.0 |= other.0;
+    }
+}
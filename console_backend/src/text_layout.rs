@@ -0,0 +1,140 @@
+use crate::markup::Span;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    // Column a line of this length starts at inside width cells
+    pub(crate) fn offset(self, length: u32, width: u32) -> u32 {
+        let free = width.saturating_sub(length);
+        match self {
+            Align::Left => 0,
+            Align::Center => free / 2,
+            Align::Right => free,
+        }
+    }
+}
+
+// Breaks text into lines at most width cells long, at whitespace where possible
+pub fn wrap_text(text: &str, width: u32) -> Vec<String> {
+    wrap_spans(&[Span::new(text)], width).iter()
+        .map(|line| line.iter().map(|span| span.text.as_str()).collect())
+        .collect()
+}
+
+// Like wrap_text, keeping every character's colors and style
+pub fn wrap_spans(spans: &[Span], width: u32) -> Vec<Vec<Span>> {
+    layout_spans(spans, width, true)
+}
+
+// Splits spans into lines at newlines and, when wrapping, wherever a line would get wider
+// than width. Whitespace at a wrap point is dropped, words longer than a line are split.
+// Leading whitespace indents the first line unless it would split the first word.
+pub(crate) fn layout_spans(spans: &[Span], width: u32, wrap: bool) -> Vec<Vec<Span>> {
+    let width = width.max(1) as usize;
+    let chars: Vec<(char, usize)> = spans.iter().enumerate()
+        .flat_map(|(index, span)| span.text.chars().map(move |c| (c, index)))
+        .collect();
+    if chars.is_empty() {
+        return Vec::new();
+    }
+
+    let mut lines = Vec::new();
+    for paragraph in chars.split(|&(c, _)| c == '\n') {
+        if !wrap {
+            lines.push(paragraph.to_vec());
+            continue;
+        }
+        let mut line: Vec<(char, usize)> = Vec::new();
+        let mut space: &[(char, usize)] = &[];
+        let mut rest = paragraph;
+        while !rest.is_empty() {
+            let is_space = rest[0].0.is_whitespace();
+            let run = rest.iter().take_while(|&&(c, _)| c.is_whitespace() == is_space).count();
+            let (token, remainder) = rest.split_at(run);
+            rest = remainder;
+            if is_space {
+                space = token;
+                continue;
+            }
+            if line.len() + space.len() + token.len() > width {
+                if !line.is_empty() {
+                    lines.push(std::mem::replace(&mut line, Vec::new()));
+                }
+                space = &[];
+            }
+            line.extend_from_slice(space);
+            space = &[];
+            for &c in token {
+                if line.len() >= width {
+                    lines.push(std::mem::replace(&mut line, Vec::new()));
+                }
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+
+    lines.iter()
+        .map(|line| {
+            let mut result: Vec<(usize, Span)> = Vec::new();
+            for &(c, index) in line {
+                match result.last_mut() {
+                    Some((last, span)) if *last == index => span.text.push(c),
+                    _ => {
+                        let mut span = spans[index].clone();
+                        span.text = c.to_string();
+                        result.push((index, span));
+                    }
+                }
+            }
+            result.into_iter().map(|(_, span)| span).collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_between_words() {
+        assert_eq!(wrap_text("the quick brown fox", 10), vec!["the quick", "brown fox"]);
+        assert_eq!(wrap_text("the quick brown fox", 9), vec!["the quick", "brown fox"]);
+        assert_eq!(wrap_text("the quick brown fox", 8), vec!["the", "quick", "brown", "fox"]);
+    }
+
+    #[test]
+    fn splits_words_longer_than_a_line() {
+        assert_eq!(wrap_text("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap_text("a abcdefgh", 4), vec!["a", "abcd", "efgh"]);
+        assert_eq!(wrap_text("abc", 0), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn newlines_always_break() {
+        assert_eq!(wrap_text("one\n\ntwo", 10), vec!["one", "", "two"]);
+        assert_eq!(wrap_text("one\n", 10), vec!["one", ""]);
+        assert!(wrap_text("", 10).is_empty());
+        assert_eq!(layout_spans(&[Span::new("no wrap\nhere")], 3, false).len(), 2);
+    }
+
+    #[test]
+    fn leading_whitespace_indents_unless_it_splits_a_word() {
+        assert_eq!(wrap_text("  lead and", 8), vec!["  lead", "and"]);
+        assert_eq!(wrap_text("  lead  and trail  ", 5), vec!["lead", "and", "trail"]);
+        assert_eq!(wrap_text("a   b", 3), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn alignment_offsets_what_is_left_over() {
+        assert_eq!(Align::Left.offset(3, 8), 0);
+        assert_eq!(Align::Center.offset(3, 8), 2);
+        assert_eq!(Align::Right.offset(3, 8), 5);
+        assert_eq!(Align::Right.offset(10, 8), 0);
+    }
+}
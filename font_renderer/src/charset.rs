@@ -0,0 +1,132 @@
+// Code page 437, the character set of the IBM PC and of most roguelike tilesheets.
+// Code 0 is drawn blank by tilesets so it maps to a space.
+pub const CP437: [char; 256] = [
+    ' ', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼',
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+    ' ', '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/',
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?',
+    '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O',
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '[', '\\', ']', '^', '_',
+    '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o',
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '~', '⌂',
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+];
+
+pub fn cp437_to_char(code: u8) -> char {
+    CP437[code as usize]
+}
+
+// Spaces come back as 32 rather than 0
+pub fn char_to_cp437(c: char) -> Option<u8> {
+    if c == ' ' {
+        return Some(32);
+    }
+    CP437.iter().position(|&other| other == c).map(|code| code as u8)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Charset {
+    chars: Vec<char>,
+}
+
+impl Charset {
+    // Keeps the first occurrence of every character, in order
+    pub fn from_chars<I: IntoIterator<Item = char>>(chars: I) -> Self {
+        let mut charset = Charset { chars: vec![] };
+        charset.extend(chars);
+        charset
+    }
+
+    pub fn ascii() -> Self {
+        Charset::from_chars((0x20u8..0x7f).map(|c| c as char))
+    }
+
+    pub fn cp437() -> Self {
+        Charset::from_chars(CP437.iter().cloned())
+    }
+
+    pub fn latin1() -> Self {
+        Charset::from_chars((0x20u32..0x7f).chain(0xa0..=0xff).filter_map(std::char::from_u32))
+    }
+
+    pub fn box_drawing() -> Self {
+        Charset::from_chars((0x2500u32..=0x257f).filter_map(std::char::from_u32))
+    }
+
+    pub fn block_elements() -> Self {
+        Charset::from_chars((0x2580u32..=0x259f).filter_map(std::char::from_u32))
+    }
+
+    pub fn arrows() -> Self {
+        Charset::from_chars((0x2190u32..=0x21ff).filter_map(std::char::from_u32))
+    }
+
+    pub fn card_suits() -> Self {
+        Charset::from_chars((0x2660u32..=0x2667).filter_map(std::char::from_u32))
+    }
+
+    pub fn union(mut self, other: &Charset) -> Self {
+        self.extend(other.chars.iter().cloned());
+        self
+    }
+
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.chars.contains(&c)
+    }
+
+    fn extend<I: IntoIterator<Item = char>>(&mut self, chars: I) {
+        for c in chars {
+            if !self.chars.contains(&c) {
+                self.chars.push(c);
+            }
+        }
+    }
+}
+
+// The characters consoles have always shipped with
+impl Default for Charset {
+    fn default() -> Self {
+        Charset::from("╚║╗╝═╔╚║╗╝abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ12345567890!@#$%^&/\\*()?<>|,.1234567890:-'\"")
+    }
+}
+
+impl From<&str> for Charset {
+    fn from(text: &str) -> Self {
+        Charset::from_chars(text.chars())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cp437_codes_map_to_their_characters() {
+        assert_eq!(cp437_to_char(0), ' ');
+        assert_eq!(cp437_to_char(1), '☺');
+        assert_eq!(cp437_to_char(b'A'), 'A');
+        assert_eq!(cp437_to_char(0xc9), '╔');
+        assert_eq!(cp437_to_char(0xdb), '█');
+        assert_eq!(cp437_to_char(0xff), '\u{A0}');
+    }
+
+    #[test]
+    fn characters_map_back_to_their_codes() {
+        for code in 1..=255u8 {
+            assert_eq!(char_to_cp437(cp437_to_char(code)), Some(code));
+        }
+        assert_eq!(char_to_cp437(' '), Some(32));
+        assert_eq!(char_to_cp437('λ'), None);
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+use rusttype::{point, Font, Scale, PositionedGlyph};
+use rusttype::gpu_cache::{Cache, CacheWriteErr};
+use crate::BoundingBox;
+use crate::metrics::{GlyphMetrics, CellMetrics};
+
+// Part of the cache texture that changed, with rows counted from the bottom like the GL texture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheUpdate {
+    Unchanged,
+    // New glyphs were added, everything cached before stayed where it was
+    Added,
+    // The cache was repacked, every bounding box handed out before is stale
+    Reordered,
+}
+
+// Rasterizes glyphs into a fixed size texture the first time they are asked for
+pub struct GlyphCache {
+    font: Font<'static>,
+    cache: Cache<'static>,
+    scale: Scale,
+    cell: CellMetrics,
+    dimensions: (u32, u32),
+    glyphs: HashMap<char, BoundingBox>,
+    // Characters that didn't fit, not tried again
+    rejected: HashSet<char>,
+}
+
+impl GlyphCache {
+    pub fn new(data: Vec<u8>, height: f32, dimensions: (u32, u32)) -> Option<Self> {
+        let font = crate::parse_font(data)?;
+        let scale = Scale::uniform(height);
+        let cell = CellMetrics::for_font(&font, scale);
+        let cache = Cache::builder()
+            .dimensions(dimensions.0, dimensions.1)
+            .pad_glyphs(true)
+            .multithread(false)
+            .build();
+        Some(GlyphCache {
+            font,
+            cache,
+            scale,
+            cell,
+            dimensions,
+            glyphs: HashMap::new(),
+            rejected: HashSet::new(),
+        })
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    pub fn cell_metrics(&self) -> CellMetrics {
+        self.cell
+    }
+
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.font.glyph(c).id().0 != 0
+    }
+
+    pub fn get(&self, c: char) -> Option<&BoundingBox> {
+        self.glyphs.get(&c)
+    }
+
+    // Makes sure every character the font has is in the texture. upload receives RGBA
+    // pixels for each changed region, bottom row first.
+    pub fn cache<I, F>(&mut self, chars: I, mut upload: F) -> Result<CacheUpdate, CacheWriteErr>
+        where I: IntoIterator<Item = char>, F: FnMut(AtlasRegion, &[u8]) {
+        let mut added = vec![];
+        for c in chars {
+            if !self.glyphs.contains_key(&c) && !self.rejected.contains(&c) && self.has_glyph(c) {
+                self.glyphs.insert(c, BoundingBox::blank());
+                added.push(c);
+            }
+        }
+        if added.is_empty() {
+            return Ok(CacheUpdate::Unchanged);
+        }
+
+        // Everything is queued again so nothing we've handed out gets evicted
+        let positioned: Vec<(char, PositionedGlyph<'static>)> = self.glyphs.keys()
+            .map(|&c| (c, self.positioned(c)))
+            .filter(|(_, glyph)| glyph.pixel_bounding_box().is_some())
+            .collect();
+        for (_, glyph) in positioned.iter() {
+            self.cache.queue_glyph(0, glyph.clone());
+        }
+
+        let texture_height = self.dimensions.1;
+        let cached = self.cache.cache_queued(|rect, coverage| {
+            let region = AtlasRegion {
+                x: rect.min.x,
+                y: texture_height - rect.max.y,
+                width: rect.width(),
+                height: rect.height(),
+            };
+            let mut pixels = Vec::with_capacity(coverage.len() * 4);
+            for row in coverage.chunks(region.width as usize).rev() {
+                for &value in row {
+                    // Same hard cut as the static atlas
+                    let alpha = if value > 51 { 255 } else { 0 };
+                    pixels.extend_from_slice(&[255, 255, 255, alpha]);
+                }
+            }
+            upload(region, &pixels);
+        });
+        let cached_by = match cached {
+            Ok(cached_by) => cached_by,
+            Err(err) => {
+                // Nothing new made it in, so they are missing rather than blank
+                for c in added {
+                    self.glyphs.remove(&c);
+                    self.rejected.insert(c);
+                }
+                return Err(err);
+            }
+        };
+
+        for (c, glyph) in positioned.iter() {
+            if let Ok(Some((uv, _))) = self.cache.rect_for(0, glyph) {
+                let (width, height) = (self.dimensions.0 as f32, self.dimensions.1 as f32);
+                let x1 = (uv.min.x * width).round() as i32;
+                let x2 = (uv.max.x * width).round() as i32;
+                let y1 = self.dimensions.1 as i32 - (uv.max.y * height).round() as i32;
+                let y2 = self.dimensions.1 as i32 - (uv.min.y * height).round() as i32;
+                let metrics = GlyphMetrics::from_glyph(glyph).unwrap_or_default();
+                let size = ((x2 - x1) as u32, (y2 - y1) as u32);
+                self.glyphs.insert(*c, BoundingBox::for_glyph((x1, y1), size, metrics, &self.cell));
+            }
+        }
+
+        Ok(match cached_by {
+            rusttype::gpu_cache::CachedBy::Adding => CacheUpdate::Added,
+            rusttype::gpu_cache::CachedBy::Reordering => CacheUpdate::Reordered,
+        })
+    }
+
+    fn positioned(&self, c: char) -> PositionedGlyph<'static> {
+        self.font.glyph(c)
+            .scaled(self.scale)
+            .positioned(point(0.0, 0.0))
+    }
+}
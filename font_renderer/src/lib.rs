@@ -1,14 +1,35 @@
 use image::{DynamicImage, Rgba, RgbaImage};
 use rusttype::{point, Font, Scale, PositionedGlyph};
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
 use unicode_normalization::UnicodeNormalization;
 
+mod charset;
+mod glyph_cache;
+mod metrics;
+mod sdf;
+mod tileset;
+
+pub use charset::{Charset, CP437, cp437_to_char, char_to_cp437};
+pub use glyph_cache::{GlyphCache, CacheUpdate, AtlasRegion};
+pub use metrics::{GlyphMetrics, CellMetrics};
+pub use tileset::{load_tileset, TilesetLayout, TilesetError};
+pub use sdf::SDF_SPREAD;
+use sdf::distance_field;
+
+// Drawn as an empty box, for characters the font can't provide
+pub const TOFU: char = '\u{FFFD}';
+
 #[derive(Debug, Clone)]
 pub struct BoundingBox {
     x1: i32,
     x2: i32,
     y1: i32,
     y2: i32,
+    metrics: GlyphMetrics,
+    // Where the glyph sits inside its cell, as fractions from the bottom left
+    cell_min: (f32, f32),
+    cell_max: (f32, f32),
 }
 
 fn s_d(x: i32, y: i32) -> f32 {
@@ -16,6 +37,45 @@ fn s_d(x: i32, y: i32) -> f32 {
 }
 
 impl BoundingBox {
+    // Stretched over the whole cell
+    fn new(x1: i32, x2: i32, y1: i32, y2: i32) -> Self {
+        BoundingBox {
+            x1,
+            x2,
+            y1,
+            y2,
+            metrics: GlyphMetrics::default(),
+            cell_min: (0.0, 0.0),
+            cell_max: (1.0, 1.0),
+        }
+    }
+
+    // min is the bottom left corner in the atlas, size the glyph's pixel size
+    pub(crate) fn for_glyph(min: (i32, i32), size: (u32, u32), metrics: GlyphMetrics, cell: &CellMetrics) -> Self {
+        let (cell_min, cell_max) = cell.place(&metrics, size);
+        BoundingBox {
+            x1: min.0,
+            x2: min.0 + size.0 as i32,
+            y1: min.1,
+            y2: min.1 + size.1 as i32,
+            metrics,
+            cell_min,
+            cell_max,
+        }
+    }
+
+    // Glyphs with no pixels, placed outside the cell so only the background is drawn
+    pub fn blank() -> Self {
+        BoundingBox {
+            x1: 0,
+            x2: 0,
+            y1: 0,
+            y2: 0,
+            metrics: GlyphMetrics::default(),
+            cell_min: (2.0, 2.0),
+            cell_max: (3.0, 3.0),
+        }
+    }
 
     pub fn width(&self) -> u32 {
         (self.x2 - self.x1) as u32
@@ -25,6 +85,27 @@ impl BoundingBox {
         (self.y2 - self.y1) as u32
     }
 
+    // pixel coordinates in the atlas, with y measured from the bottom like the GL texture
+    pub fn min(&self) -> (i32, i32) {
+        (self.x1, self.y1)
+    }
+
+    pub fn max(&self) -> (i32, i32) {
+        (self.x2, self.y2)
+    }
+
+    pub fn metrics(&self) -> &GlyphMetrics {
+        &self.metrics
+    }
+
+    pub fn cell_min(&self) -> (f32, f32) {
+        self.cell_min
+    }
+
+    pub fn cell_max(&self) -> (f32, f32) {
+        self.cell_max
+    }
+
     pub fn top_left(&self, scale: (i32, i32)) -> (f32, f32) {
         (s_d(self.x1, scale.0), s_d(self.y2, scale.1))
     }
@@ -42,84 +123,161 @@ impl BoundingBox {
     }
 }
 
-fn prepare_glyphs(data:Vec<u8>, text: &str, height: f32) -> (DynamicImage, Vec<PositionedGlyph>, (u32, u32)) {
-    let font = Font::from_bytes(data).unwrap();
-    let scale = Scale::uniform(height);
+// Space left around every glyph so sampling never bleeds into a neighbour
+const PADDING: u32 = 2;
+// Pixel height glyphs are rasterized at for the atlas
+const ATLAS_HEIGHT: f32 = 100.0;
 
-    // let text = "ABC";
-    let color = (255, 255, 255);
-    let v_metrics = font.v_metrics(scale);
-    let glyphs_height = (v_metrics.ascent - v_metrics.descent).ceil() as u32;
-    let glyphs: Vec<_> = font
-        .layout(text, scale, point(0.0, v_metrics.ascent))
-        .collect();
+pub fn load_bitmap(data: Vec<u8>) -> (DynamicImage, HashMap<char, BoundingBox>) {
+    load_bitmap_with_charset(data, &Charset::default()).unwrap()
+}
 
+// Cell size of the atlases load_bitmap_with_charset and load_sdf_with_charset build
+pub fn atlas_cell_metrics(data: &[u8]) -> Option<CellMetrics> {
+    let font = parse_font(data)?;
+    Some(CellMetrics::for_font(&font, Scale::uniform(ATLAS_HEIGHT)))
+}
 
-    let glyphs_width = {
-        let min_x = glyphs
-            .first()
-            .map(|g| g.pixel_bounding_box().unwrap().min.x)
-            .unwrap();
-        let max_x = glyphs
-            .last()
-            .map(|g| g.pixel_bounding_box().unwrap().max.x)
-            .unwrap();
-        (max_x - min_x) as u32
-    };
+// rusttype reads the first four bytes before checking there are that many
+pub(crate) fn parse_font<'a, B: Into<rusttype::SharedBytes<'a>>>(data: B) -> Option<Font<'a>> {
+    let data = data.into();
+    if data.len() < 4 {
+        return None;
+    }
+    Font::from_bytes(data).ok()
+}
+
+// Characters the font doesn't have are left out of the atlas, None if data isn't a font
+pub fn load_bitmap_with_charset(data: Vec<u8>, charset: &Charset) -> Option<(DynamicImage, HashMap<char, BoundingBox>)> {
+    build_atlas(data, charset, AtlasMode::Bitmap)
+}
 
-    let mut image: RgbaImage = DynamicImage::new_rgba8(glyphs_width + height as u32, glyphs_height).to_rgba();
+// Like load_bitmap_with_charset, but the alpha channel holds the distance to the glyph's outline,
+// 0.5 being right on it, so it stays sharp when scaled. Meant to be sampled linearly.
+pub fn load_sdf_with_charset(data: Vec<u8>, charset: &Charset) -> Option<(DynamicImage, HashMap<char, BoundingBox>)> {
+    build_atlas(data, charset, AtlasMode::Sdf)
+}
 
-    for x in 0..image.width() {
-        for y in 0..image.height() {
-            image.put_pixel(
-                x,
-                y,
-                Rgba([255, 255, 255, 0]),
-            )
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AtlasMode {
+    Bitmap,
+    Sdf,
+}
+
+fn build_atlas(data: Vec<u8>, charset: &Charset, mode: AtlasMode) -> Option<(DynamicImage, HashMap<char, BoundingBox>)> {
+    let font = parse_font(data)?;
+    let scale = Scale::uniform(ATLAS_HEIGHT);
+    let cell = CellMetrics::for_font(&font, scale);
+    // Distance fields need room to fall off around the glyph
+    let spread = match mode {
+        AtlasMode::Bitmap => 0,
+        AtlasMode::Sdf => SDF_SPREAD,
+    };
+
+    let mut map = HashMap::new();
+    let mut glyphs: Vec<(char, PositionedGlyph)> = vec![];
+    for &c in charset.chars().iter().filter(|c| !c.is_control() && font.glyph(**c).id().0 != 0) {
+        let glyph = font.glyph(c).scaled(scale).positioned(point(0.0, 0.0));
+        if glyph.pixel_bounding_box().is_some() {
+            glyphs.push((c, glyph));
+        } else {
+            map.insert(c, BoundingBox::blank());
         }
     }
-    for glyph in glyphs.iter() {
-        if let Some(bounding_box) = glyph.pixel_bounding_box() {
-            glyph.draw(|x, y, v| {
-                if v > 0.2 {
-                    image.put_pixel(
-                        x + bounding_box.min.x as u32,
-                        y + bounding_box.min.y as u32,
-                        Rgba([color.0, color.1, color.2, 255]),
-                    )
-                }
-            });
+    map.insert(' ', BoundingBox::blank());
+
+    let tofu_size = (cell.width.ceil() as u32, cell.height.ceil() as u32);
+    let mut sizes: Vec<(u32, u32)> = glyphs.iter()
+        .filter_map(|(_, glyph)| glyph.pixel_bounding_box())
+        .map(|bounding_box| (bounding_box.width() as u32, bounding_box.height() as u32))
+        .collect();
+    sizes.push(tofu_size);
+    let padded: Vec<(u32, u32)> = sizes.iter()
+        .map(|(width, height)| (width + 2 * spread, height + 2 * spread))
+        .collect();
+    let ((atlas_width, atlas_height), positions) = pack(&padded);
+
+    let mut image = RgbaImage::from_pixel(atlas_width, atlas_height, Rgba([255, 255, 255, 0]));
+    let mut coverages: Vec<Vec<f32>> = glyphs.iter()
+        .zip(padded.iter())
+        .map(|((_, glyph), &(width, height))| {
+            let mut coverage = vec![0.0; (width * height) as usize];
+            glyph.draw(|x, y, v| coverage[((y + spread) * width + x + spread) as usize] = v);
+            coverage
+        })
+        .collect();
+    coverages.push(tofu_coverage(padded[padded.len() - 1], spread));
+
+    for ((coverage, &(width, height)), &(x, y)) in coverages.iter().zip(padded.iter()).zip(positions.iter()) {
+        let alphas: Vec<u8> = match mode {
+            // Same hard cut as always, nearest sampling keeps it crisp at the atlas size
+            AtlasMode::Bitmap => coverage.iter().map(|&v| if v > 0.2 { 255 } else { 0 }).collect(),
+            AtlasMode::Sdf => distance_field(coverage, width, height),
+        };
+        for (index, &alpha) in alphas.iter().enumerate() {
+            let (px, py) = (index as u32 % width, index as u32 / width);
+            image.put_pixel(x + px, y + py, Rgba([255, 255, 255, alpha]));
         }
     }
-    (DynamicImage::ImageRgba8(image), glyphs, (glyphs_width, glyphs_height))
+
+    // The image is flipped on upload, so the atlas is addressed from the bottom
+    let inner_min = |(x, y): (u32, u32), height: u32| {
+        ((x + spread) as i32, (atlas_height - y - spread - height) as i32)
+    };
+    for (((c, glyph), &size), &position) in glyphs.iter().zip(sizes.iter()).zip(positions.iter()) {
+        let metrics = GlyphMetrics::from_glyph(glyph).unwrap_or_default();
+        map.insert(*c, BoundingBox::for_glyph(inner_min(position, size.1), size, metrics, &cell));
+    }
+    let (x, y) = inner_min(positions[positions.len() - 1], tofu_size.1);
+    map.insert(TOFU, BoundingBox::new(x, x + tofu_size.0 as i32, y, y + tofu_size.1 as i32));
+
+    Some((DynamicImage::ImageRgba8(image), map))
 }
 
-pub fn load_bitmap(data: Vec<u8>) -> (DynamicImage, HashMap<char, BoundingBox>) {
-    let text = "╚║╗╝═╔╚║╗╝abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ12345567890!@#$%^&/\\*()?<>|,.1234567890:-'\"";
-    let height = 100.0;
-    let (image, glyphs, (glyphs_width, glyphs_height)) = prepare_glyphs(data, text, height);
+// Shelf packs rectangles tallest first into a power of two wide atlas, returning its size and
+// the top left corner of every rectangle
+fn pack(sizes: &[(u32, u32)]) -> ((u32, u32), Vec<(u32, u32)>) {
+    let area: u32 = sizes.iter().map(|(width, height)| (width + PADDING) * (height + PADDING)).sum();
+    let widest = sizes.iter().map(|(width, _)| *width).max().unwrap_or(0);
+    let atlas_width = ((area as f32).sqrt().ceil() as u32)
+        .max(widest + 2 * PADDING)
+        .next_power_of_two();
 
-    let mut map = HashMap::new();
-    let mut counter = 0;
-
-    for glyph in glyphs.iter() {
-        if let Some(bounding_box) = glyph.pixel_bounding_box() {
-            map.insert(text.chars().nth(counter).unwrap(), BoundingBox{
-                x1: glyph.position().x as i32,
-                x2: bounding_box.max.x,
-                y1: 0,
-                y2: height as i32,
-            });
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&index| Reverse(sizes[index].1));
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut shelf_height) = (PADDING, PADDING, 0);
+    for index in order {
+        let (width, height) = sizes[index];
+        if x + width + PADDING > atlas_width {
+            x = PADDING;
+            y += shelf_height + PADDING;
+            shelf_height = 0;
         }
-        counter += 1;
+        positions[index] = (x, y);
+        x += width + PADDING;
+        shelf_height = shelf_height.max(height);
     }
+    ((atlas_width, y + shelf_height + PADDING), positions)
+}
 
-    map.insert(' ', BoundingBox{
-        x1: glyphs_width as i32,
-        x2: glyphs_width as i32 + 1,
-        y1: 0,
-        y2: 1,
-    });
-
-    (image, map)
+// An outlined box filling most of a cell, inside a frame of spread empty pixels
+fn tofu_coverage(padded: (u32, u32), spread: u32) -> Vec<f32> {
+    let (width, height) = (padded.0 - 2 * spread, padded.1 - 2 * spread);
+    let margin = (width / 5, height / 10);
+    let thickness = (height / 8).max(1);
+    let (left, right) = (spread + margin.0, spread + width - margin.0);
+    let (top, base) = (spread + margin.1, spread + height - margin.1);
+    let mut coverage = vec![0.0; (padded.0 * padded.1) as usize];
+    for px in left..right {
+        for py in top..base {
+            let on_edge = px < left + thickness || px >= right - thickness
+                || py < top + thickness || py >= base - thickness;
+            if on_edge {
+                coverage[(py * padded.0 + px) as usize] = 1.0;
+            }
+        }
+    }
+    coverage
 }
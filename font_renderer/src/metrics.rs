@@ -0,0 +1,63 @@
+use rusttype::{Font, Scale, PositionedGlyph};
+
+// Pixel metrics of a single glyph at the atlas scale
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GlyphMetrics {
+    // From the pen position to the left edge, and from the baseline up to the top edge
+    pub bearing: (f32, f32),
+    pub advance: f32,
+    // From the baseline down to the bottom edge, negative for glyphs sitting above it
+    pub baseline_offset: f32,
+}
+
+impl GlyphMetrics {
+    pub fn from_glyph(glyph: &PositionedGlyph) -> Option<Self> {
+        let bounding_box = glyph.pixel_bounding_box()?;
+        let origin = glyph.position();
+        Some(GlyphMetrics {
+            bearing: (bounding_box.min.x as f32 - origin.x, origin.y - bounding_box.min.y as f32),
+            advance: glyph.unpositioned().h_metrics().advance_width,
+            baseline_offset: bounding_box.max.y as f32 - origin.y,
+        })
+    }
+}
+
+// Size of a console cell for a font, every glyph is placed relative to it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellMetrics {
+    pub width: f32,
+    pub height: f32,
+    // From the bottom of the cell up to the baseline
+    pub baseline: f32,
+}
+
+impl CellMetrics {
+    // As wide as an 'M' and as tall as a line of text
+    pub fn for_font(font: &Font, scale: Scale) -> Self {
+        let v_metrics = font.v_metrics(scale);
+        CellMetrics {
+            width: font.glyph('M').scaled(scale).h_metrics().advance_width,
+            height: v_metrics.ascent - v_metrics.descent,
+            baseline: -v_metrics.descent,
+        }
+    }
+
+    // Bottom left and top right of a glyph inside the cell, as fractions from the bottom left.
+    // Glyphs are centered on their advance, anything wider than the cell is narrowed to fit.
+    pub fn place(&self, metrics: &GlyphMetrics, size: (u32, u32)) -> ((f32, f32), (f32, f32)) {
+        let width = size.0 as f32;
+        let left = if width > self.width {
+            0.0
+        } else {
+            let left = (self.width - metrics.advance) / 2.0 + metrics.bearing.0;
+            left.max(0.0).min(self.width - width)
+        };
+        let right = left + width.min(self.width);
+        let bottom = self.baseline - metrics.baseline_offset;
+        let top = bottom + size.1 as f32;
+        (
+            (left / self.width, bottom / self.height),
+            (right / self.width, top / self.height),
+        )
+    }
+}
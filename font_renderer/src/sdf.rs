@@ -0,0 +1,68 @@
+// How far from the outline, in atlas pixels, distances are still told apart. Every glyph gets
+// this much room around it so the field can fall off smoothly.
+pub const SDF_SPREAD: u32 = 8;
+
+const FAR: i32 = 1 << 14;
+
+// Turns coverage into alpha where 0.5 is the outline, more is inside and less is outside
+pub(crate) fn distance_field(coverage: &[f32], width: u32, height: u32) -> Vec<u8> {
+    let inside: Vec<bool> = coverage.iter().map(|&value| value > 0.5).collect();
+    let to_inside = nearest_distances(&inside, width as i32, height as i32, true);
+    let to_outside = nearest_distances(&inside, width as i32, height as i32, false);
+    to_inside.iter().zip(to_outside.iter())
+        .map(|(to_inside, to_outside)| {
+            // Negative inside the glyph, positive outside it
+            let signed = to_inside - to_outside;
+            let alpha = 0.5 - signed / (2.0 * SDF_SPREAD as f32);
+            (alpha.max(0.0).min(1.0) * 255.0).round() as u8
+        })
+        .collect()
+}
+
+// Distance from every pixel to the nearest one whose inside flag equals target, using the
+// two pass 8SSEDT sweep
+fn nearest_distances(inside: &[bool], width: i32, height: i32, target: bool) -> Vec<f32> {
+    let mut grid: Vec<(i32, i32)> = inside.iter()
+        .map(|&value| if value == target { (0, 0) } else { (FAR, FAR) })
+        .collect();
+
+    let compare = |grid: &mut Vec<(i32, i32)>, x: i32, y: i32, offset: (i32, i32)| {
+        let (other_x, other_y) = (x + offset.0, y + offset.1);
+        if other_x < 0 || other_y < 0 || other_x >= width || other_y >= height {
+            return;
+        }
+        let other = grid[(other_y * width + other_x) as usize];
+        let candidate = (other.0 + offset.0, other.1 + offset.1);
+        let current = &mut grid[(y * width + x) as usize];
+        if length_squared(candidate) < length_squared(*current) {
+            *current = candidate;
+        }
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            for &offset in &[(-1, 0), (0, -1), (-1, -1), (1, -1)] {
+                compare(&mut grid, x, y, offset);
+            }
+        }
+        for x in (0..width).rev() {
+            compare(&mut grid, x, y, (1, 0));
+        }
+    }
+    for y in (0..height).rev() {
+        for x in (0..width).rev() {
+            for &offset in &[(1, 0), (0, 1), (-1, 1), (1, 1)] {
+                compare(&mut grid, x, y, offset);
+            }
+        }
+        for x in 0..width {
+            compare(&mut grid, x, y, (-1, 0));
+        }
+    }
+
+    grid.iter().map(|&offset| (length_squared(offset) as f32).sqrt()).collect()
+}
+
+fn length_squared(offset: (i32, i32)) -> i32 {
+    offset.0 * offset.0 + offset.1 * offset.1
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::{error, fmt};
+use image::{DynamicImage, GenericImage, Rgba, RgbaImage};
+use crate::{tofu_coverage, BoundingBox, TOFU};
+
+// How the tiles of a sheet are laid out, tile 0 being the top left one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TilesetLayout {
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl TilesetLayout {
+    pub fn grid(columns: u32, rows: u32) -> Self {
+        TilesetLayout {
+            columns,
+            rows,
+        }
+    }
+
+    // Fails for layouts without tiles and images with less than a pixel per tile
+    pub fn tile_size(&self, image_size: (u32, u32)) -> Result<(u32, u32), TilesetError> {
+        if self.columns == 0 || self.rows == 0 {
+            return Err(TilesetError::EmptyLayout(*self));
+        }
+        let size = (image_size.0 / self.columns, image_size.1 / self.rows);
+        if size.0 == 0 || size.1 == 0 {
+            return Err(TilesetError::ImageTooSmall { image_size, layout: *self });
+        }
+        Ok(size)
+    }
+}
+
+impl Default for TilesetLayout {
+    // The usual 16x16 sheet of a whole code page
+    fn default() -> Self {
+        TilesetLayout::grid(16, 16)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TilesetError {
+    EmptyLayout(TilesetLayout),
+    ImageTooSmall { image_size: (u32, u32), layout: TilesetLayout },
+}
+
+impl fmt::Display for TilesetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TilesetError::EmptyLayout(layout) =>
+                write!(f, "Tileset layout of {}x{} tiles has no tiles", layout.columns, layout.rows),
+            TilesetError::ImageTooSmall { image_size, layout } =>
+                write!(f, "Tileset image of {}x{} pixels is too small for {}x{} tiles",
+                       image_size.0, image_size.1, layout.columns, layout.rows),
+        }
+    }
+}
+
+impl error::Error for TilesetError {}
+
+// Cuts a tile sheet into glyphs, codepage[n] being the character drawn by tile n counted row by
+// row. Sheets without transparency use the color of the top left pixel as the background. Like
+// the other atlases the map always has ' ' and TOFU, a tofu box being drawn in a row of its own
+// below the sheet when the codepage doesn't give one.
+pub fn load_tileset(image: &DynamicImage, layout: TilesetLayout, codepage: &[char]) -> Result<(DynamicImage, HashMap<char, BoundingBox>), TilesetError> {
+    let mut atlas = image.to_rgba();
+    let (width, height) = atlas.dimensions();
+    let (tile_width, tile_height) = layout.tile_size((width, height))?;
+    if atlas.pixels().all(|pixel| pixel[3] == 255) {
+        key_out(&mut atlas);
+    }
+
+    let tile_count = (layout.columns * layout.rows) as usize;
+    let has_tofu = codepage.iter().take(tile_count).any(|&c| c == TOFU);
+    if !has_tofu {
+        atlas = with_tofu_row(&atlas, (tile_width, tile_height));
+    }
+    let height = atlas.dimensions().1;
+
+    let mut map = HashMap::new();
+    for (index, &c) in codepage.iter().enumerate().take(tile_count) {
+        let (column, row) = (index as u32 % layout.columns, index as u32 / layout.columns);
+        // The image is flipped on upload, so rows are counted from the bottom
+        let bottom = height - (row + 1) * tile_height;
+        map.entry(c).or_insert_with(|| BoundingBox::new(
+            (column * tile_width) as i32,
+            ((column + 1) * tile_width) as i32,
+            bottom as i32,
+            (bottom + tile_height) as i32,
+        ));
+    }
+
+    map.entry(' ').or_insert_with(BoundingBox::blank);
+    if !has_tofu {
+        // The row added under the sheet, which is the bottom of the flipped atlas
+        map.insert(TOFU, BoundingBox::new(0, tile_width as i32, 0, tile_height as i32));
+    }
+
+    Ok((DynamicImage::ImageRgba8(atlas), map))
+}
+
+// The sheet with a transparent row of tiles added below it, a tofu box in its first tile
+fn with_tofu_row(sheet: &RgbaImage, tile_size: (u32, u32)) -> RgbaImage {
+    let (width, height) = sheet.dimensions();
+    let mut atlas = RgbaImage::from_pixel(width, height + tile_size.1, Rgba([255, 255, 255, 0]));
+    atlas.copy_from(sheet, 0, 0);
+    for (index, &coverage) in tofu_coverage(tile_size, 0).iter().enumerate() {
+        if coverage > 0.0 {
+            let (x, y) = (index as u32 % tile_size.0, index as u32 / tile_size.0);
+            atlas.put_pixel(x, height + y, Rgba([255, 255, 255, 255]));
+        }
+    }
+    atlas
+}
+
+fn key_out(image: &mut RgbaImage) {
+    let key = *image.get_pixel(0, 0);
+    for pixel in image.pixels_mut() {
+        if pixel[0] == key[0] && pixel[1] == key[1] && pixel[2] == key[2] {
+            *pixel = Rgba([pixel[0], pixel[1], pixel[2], 0]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+    use crate::CP437;
+
+    // 2x2 tiles of 2x2 pixels on an opaque black background, each tile lighting its bottom right pixel
+    fn sheet() -> DynamicImage {
+        let mut image = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255]));
+        for &(x, y) in &[(1, 1), (3, 1), (1, 3), (3, 3)] {
+            image.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+        }
+        DynamicImage::ImageRgba8(image)
+    }
+
+    #[test]
+    fn tiles_are_counted_from_the_top_left() {
+        let (atlas, map) = load_tileset(&sheet(), TilesetLayout::grid(2, 2), &['a', 'b', 'c', 'd']).unwrap();
+        // The tofu row makes the atlas one tile taller, and rows count up from the bottom
+        assert_eq!(atlas.dimensions(), (4, 6));
+        let corners = |c: char| (map[&c].min(), map[&c].max());
+        assert_eq!(corners('a'), ((0, 4), (2, 6)));
+        assert_eq!(corners('b'), ((2, 4), (4, 6)));
+        assert_eq!(corners('c'), ((0, 2), (2, 4)));
+        assert_eq!(corners('d'), ((2, 2), (4, 4)));
+    }
+
+    #[test]
+    fn opaque_sheets_key_out_the_top_left_color() {
+        let (atlas, _) = load_tileset(&sheet(), TilesetLayout::grid(2, 2), &['a']).unwrap();
+        let atlas = atlas.to_rgba();
+        assert_eq!(atlas.get_pixel(0, 0)[3], 0);
+        assert_eq!(atlas.get_pixel(1, 1)[3], 255);
+    }
+
+    #[test]
+    fn space_and_tofu_are_always_there() {
+        let (atlas, map) = load_tileset(&sheet(), TilesetLayout::grid(2, 2), &['a', 'b']).unwrap();
+        assert_eq!(map[&' '].width(), 0);
+        assert_eq!((map[&TOFU].min(), map[&TOFU].max()), ((0, 0), (2, 2)));
+        // Rows below the sheet, the first tile holding the box
+        let atlas = atlas.to_rgba();
+        assert_eq!(atlas.get_pixel(0, 4)[3], 255);
+        assert_eq!(atlas.get_pixel(3, 5)[3], 0);
+    }
+
+    #[test]
+    fn codepage_tiles_win_over_the_fallbacks() {
+        let (atlas, map) = load_tileset(&sheet(), TilesetLayout::grid(2, 2), &[' ', TOFU, 'c']).unwrap();
+        assert_eq!(atlas.dimensions(), (4, 4));
+        assert_eq!(map[&' '].min(), (0, 2));
+        assert_eq!(map[&TOFU].min(), (2, 2));
+    }
+
+    #[test]
+    fn characters_past_the_last_tile_are_ignored() {
+        let (_, map) = load_tileset(&sheet(), TilesetLayout::grid(2, 2), &CP437).unwrap();
+        assert!(map.contains_key(&'♥'));
+        assert!(!map.contains_key(&'♦'));
+    }
+
+    #[test]
+    fn bad_layouts_are_errors() {
+        assert_eq!(load_tileset(&sheet(), TilesetLayout::grid(0, 2), &CP437).err(),
+                   Some(TilesetError::EmptyLayout(TilesetLayout::grid(0, 2))));
+        assert!(load_tileset(&sheet(), TilesetLayout::grid(8, 2), &CP437).is_err());
+    }
+}
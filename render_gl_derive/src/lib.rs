@@ -6,7 +6,7 @@ extern crate syn;
 
 use proc_macro::TokenStream;
 
-#[proc_macro_derive(VertexAttribPointers, attributes(location))]
+#[proc_macro_derive(VertexAttribPointers, attributes(location, divisor))]
 pub fn vertex_attrib_pointers_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast = syn::parse_macro_input!(input as syn::DeriveInput);
     let generated = generate_impl(&ast);
@@ -17,7 +17,9 @@ fn generate_impl(ast: &syn::DeriveInput) -> proc_macro2::TokenStream {
     let ident = &ast.ident;
     let generics = &ast.generics;
     let where_clause = &ast.generics.where_clause;
-    let fields_vertex_attrib_pointer = generate_vertex_attrib_pointer_calls(&ast.data);
+    // A divisor on the struct applies to every field, e.g. #[divisor = 1] for per-instance data
+    let divisor = find_usize_attribute(&ast.attrs, "divisor", &ident.to_string());
+    let fields_vertex_attrib_pointer = generate_vertex_attrib_pointer_calls(&ast.data, divisor);
 
     quote!(
         impl #ident #generics #where_clause {
@@ -30,16 +32,16 @@ fn generate_impl(ast: &syn::DeriveInput) -> proc_macro2::TokenStream {
     )
 }
 
-fn generate_vertex_attrib_pointer_calls(body: &syn::Data) -> Vec<proc_macro2::TokenStream> {
+fn generate_vertex_attrib_pointer_calls(body: &syn::Data, divisor: Option<usize>) -> Vec<proc_macro2::TokenStream> {
     match body {
         &syn::Data::Struct(syn::DataStruct {fields: ref s, ..}) =>
-            s.iter().map(generate_struct_field_vertex_attrib_pointer_call).collect(),
+            s.iter().map(|field| generate_struct_field_vertex_attrib_pointer_call(field, divisor)).collect(),
         &syn::Data::Enum(_) => panic!("VertexAttribPointers cannot be implemented for enums."),
         &syn::Data::Union(_) => panic!("VertexAttribPointers cannot be implemented for unions."),
     }
 }
 
-fn generate_struct_field_vertex_attrib_pointer_call(field: &syn::Field) -> proc_macro2::TokenStream {
+fn generate_struct_field_vertex_attrib_pointer_call(field: &syn::Field, divisor: Option<usize>) -> proc_macro2::TokenStream {
     let field_name = match field.ident {
         Some(ref i) => format!("{}", i),
         None => String::from(""),
@@ -69,12 +71,39 @@ fn generate_struct_field_vertex_attrib_pointer_call(field: &syn::Field) -> proc_
         "Field {} location attribute missing a value", field_name)
     );
 
+    let divisor_call = find_usize_attribute(&field.attrs, "divisor", &field_name)
+        .or(divisor)
+        .map(|divisor| quote! {
+            gl.VertexAttribDivisor(location as ::gl::types::GLuint, #divisor as ::gl::types::GLuint);
+        });
+
     let field_type = &field.ty;
     quote! {
         let location = #location_value;
         unsafe {
             #field_type::vertex_attrib_pointer(gl, stride, location, offset);
+            #divisor_call
         }
         let offset = offset + ::std::mem::size_of::<#field_type>();
     }
+}
+
+fn find_usize_attribute(attrs: &[syn::Attribute], name: &str, owner: &str) -> Option<usize> {
+    attrs
+        .iter()
+        .find(|a| a.path.is_ident(name))
+        .map(|attr| attr
+            .parse_meta()
+            .map(|meta| match meta {
+                syn::Meta::NameValue(ref name_value) => {
+                    match &name_value.lit {
+                        syn::Lit::Int(int) => int.value() as usize,
+                        _ => panic!("{} {} attribute value must contain an integer", owner, name),
+                    }
+                }
+                _ => panic!("{} {} should have the structure #[{} = ?]", owner, name, name)
+            })
+            .unwrap_or_else(|_| panic!(
+                "{} {} attribute missing a value", owner, name)
+            ))
 }
\ No newline at end of file
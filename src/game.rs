@@ -3,14 +3,16 @@ use crate::game_handler::{GameContext, InputEvent, InputEventData};
 use glutin::{
     dpi::LogicalSize,
     window::Window,
-    event::VirtualKeyCode,
+    event::{VirtualKeyCode, MouseButton},
 };
 
 use nalgebra_glm::Vec3;
 
-use console_backend::{ColorBuffer, Camera, resources::Resources, Console, data, Color, colors, ConsoleBuilder, Transformer};
+use console_backend::{ColorBuffer, Camera, resources::Resources, data, Color, colors, ConsoleBuilder, ConsoleHandle, ConsoleRoot, Transformer, Assets, Anchor};
 
+use std::fs;
 use std::path::Path;
+use std::rc::Rc;
 use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 use failure::_core::time::Duration;
@@ -22,6 +24,10 @@ use std::cmp;
 use crate::fov::calculate_fov;
 use crate::widgets::menu::Menu;
 
+const INVENTORY_MIN_WIDTH: usize = 24;
+// Written next to the working directory when the map is dumped
+const MAP_DUMP_NAME: &str = "map_dump";
+
 pub trait Game {
     fn new(context: &GameContext, size: LogicalSize) -> Self;
     fn render(&mut self, context: &GameContext);
@@ -47,17 +53,21 @@ pub struct GameImpl {
     has_moved: bool,
     color_buffer: ColorBuffer,
     camera: Camera,
-    console: Console,
+    root: ConsoleRoot,
+    console: ConsoleHandle,
     inventory: Vec<Object>,
-    console_term: Console,
+    console_term: ConsoleHandle,
     map: Map,
     objects: Vec<Object>,
     keyboard: HashMap<VirtualKeyCode, bool>,
     input_limiter: Instant,
     message_log: ScrollingMessageConsole,
-    game_over: Console,
+    game_over: ConsoleHandle,
     active_menu: Option<Menu>,
+    // Menu option the left button went down on
+    pressed_option: Option<usize>,
     font_size: (f32, f32),
+    assets: Assets,
 }
 
 impl GameImpl {
@@ -81,22 +91,38 @@ impl GameImpl {
         UseResult::UsedUp
     }
 
-    fn inventory_menu(&mut self, header: String, context: &GameContext) {
+    // Saves the map as it's shown, as text, ANSI and HTML, for pasting into bug reports
+    fn dump_map(&mut self) {
+        let result = {
+            let console = self.console.borrow();
+            fs::write(format!("{}.txt", MAP_DUMP_NAME), console.to_plain_text())
+                .and_then(|_| fs::write(format!("{}.ans", MAP_DUMP_NAME), console.to_ansi()))
+                .and_then(|_| fs::write(format!("{}.html", MAP_DUMP_NAME), console.to_html()))
+        };
+        match result {
+            Ok(()) => self.message_log.add_message(&format!("Map saved to {}.txt, .ans and .html", MAP_DUMP_NAME)),
+            Err(err) => self.message_log.add_colored_message(&format!("Couldn't save the map: {}", err), *theme::RED_ALERT_TEXT),
+        }
+    }
+
+    fn inventory_menu(&mut self, header: String) {
         let options = if self.inventory.len() == 0 {
             vec!["Inventory is empty.".into()]
         } else {
           self.inventory.iter().map(|item| {item.name.clone()}).collect()
         };
-        let mut max = header.len();
+        // Wide enough for every "(a) option" line, the header wraps to fit
+        let mut max = INVENTORY_MIN_WIDTH;
         options.iter().for_each(|option| {
-            if max < option.len() {
-                max = option.len()
+            if max < option.len() + 4 {
+                max = option.len() + 4
             }});
-        let menu = Menu::new(&context.gl,
+        let menu = Menu::new(&self.assets,
                              header,
                              options,
                              (max + 1) as u32,
                              self.font_size,
+                             &mut self.root,
                              &self.console);
 
         self.active_menu = Some(menu);
@@ -124,7 +150,7 @@ impl GameImpl {
         }
     }
 
-    fn process_input(&mut self, pending_input: InputEvent, context: &GameContext) -> PlayerAction {
+    fn process_input(&mut self, pending_input: InputEvent, _context: &GameContext) -> PlayerAction {
         match pending_input {
             InputEvent::KeyPressed(
                 InputEventData {
@@ -168,7 +194,11 @@ impl GameImpl {
                             PlayerAction::DidNotTakeTurn
                         }
                         VirtualKeyCode::I => {
-                            self.inventory_menu("Press the key next to an item to use it, or any other to cancel.".to_string(), &context);
+                            self.inventory_menu("Press the key next to an item to use it, or any other to cancel.".to_string());
+                            PlayerAction::DidNotTakeTurn
+                        }
+                        VirtualKeyCode::F12 => {
+                            self.dump_map();
                             PlayerAction::DidNotTakeTurn
                         }
                         _ => PlayerAction::DidNotTakeTurn
@@ -176,10 +206,66 @@ impl GameImpl {
                 }
 
             }
+            InputEvent::CursorMoved(
+                InputEventData {
+                    data: position,
+                    ..
+                }) => {
+                let option = self.menu_option_at(position);
+                if let Some(menu) = &mut self.active_menu {
+                    menu.hover(option);
+                }
+                PlayerAction::DidNotTakeTurn
+            }
+            InputEvent::MousePressed(
+                InputEventData {
+                    data: (MouseButton::Left, position),
+                    ..
+                }) => {
+                self.pressed_option = self.menu_option_at(position);
+                PlayerAction::DidNotTakeTurn
+            }
+            // A menu option is picked when the button goes up on the one it went down on.
+            // Releasing on another option or outside the menu closes it, its header and blank
+            // rows do nothing.
+            InputEvent::MouseReleased(
+                InputEventData {
+                    data: (MouseButton::Left, position),
+                    ..
+                }) => {
+                let close = match &self.active_menu {
+                    Some(menu) => match self.root.cell_at(position) {
+                        Some((console, cell)) if Rc::ptr_eq(&console, &menu.console) => match menu.option_at(cell) {
+                            Some(option) if Some(option) == self.pressed_option => {
+                                self.use_item(option);
+                                true
+                            },
+                            option => option.is_some(),
+                        },
+                        _ => true,
+                    },
+                    None => false,
+                };
+                if close {
+                    self.active_menu = None;
+                    self.has_moved = true;
+                }
+                self.pressed_option = None;
+                PlayerAction::DidNotTakeTurn
+            }
             _ => PlayerAction::DidNotTakeTurn
         }
     }
 
+    // The option of the open menu under a window position
+    fn menu_option_at(&self, position: (f32, f32)) -> Option<usize> {
+        let menu = self.active_menu.as_ref()?;
+        match self.root.cell_at(position) {
+            Some((console, cell)) if Rc::ptr_eq(&console, &menu.console) => menu.option_at(cell),
+            _ => None,
+        }
+    }
+
     fn player_move_or_attack(&mut self, dx: i32, dy: i32) {
         if self.objects[0].alive {
             let (x, y) = (self.objects[0].position.0 + dx, self.objects[0].position.1 + dy);
@@ -212,19 +298,9 @@ impl GameImpl {
         }
     }
 
-    fn set_window_ratios(&mut self, size: LogicalSize) {
-        let transformer = Transformer::AspectRatio(16.0 / 12.0, (size.width / size.height) as f32);
-        transformer.apply(&mut self.console)
-            .apply(&mut self.console_term)
-            .apply(&mut self.game_over)
-            .apply(&mut self.message_log.console);
-        if let Some(menu) = &mut self.active_menu {
-            transformer.apply(&mut menu.console);
-        }
-    }
-
     fn init_level_buffer(&mut self) {
-        self.console.clear();
+        let mut console = self.console.borrow_mut();
+        console.clear();
         let visible_tiles = calculate_fov(self.objects[0].position, 10, &self.map);
         for obj in self.objects.iter() {
             let layer = match obj.blocks {
@@ -232,7 +308,7 @@ impl GameImpl {
                 false => 2,
             };
             if visible_tiles.contains(&(obj.position)) {
-                self.console.put_char(obj.glyph, obj.position.0, obj.position.1, obj.color, Some(*colors::CLEAR), layer);
+                console.put_char(obj.glyph, obj.position.0, obj.position.1, obj.color, Some(*colors::CLEAR), layer);
             }
         }
         for x in 0..self.map.len() {
@@ -243,7 +319,7 @@ impl GameImpl {
                     } else {
                         *theme::COLOR_DARK_FLOOR
                     };
-                    self.console.put_char(
+                    console.put_char(
                         ' ',
                         x as i32, y as i32, *colors::CLEAR, Some(color), 1);
 
@@ -253,7 +329,7 @@ impl GameImpl {
                     } else {
                         *theme::COLOR_DARK_WALL
                     };
-                    self.console.put_char(
+                    console.put_char(
                         ' ',
                         x as i32, y as i32, *colors::CLEAR, Some(color), 1)
                 }
@@ -266,11 +342,15 @@ impl Game for GameImpl  {
     fn new(context: &GameContext, size: LogicalSize) -> Self {
         let window: &Window = context.window.window();
         let res = Resources::from_relative_exe_path(Path::new("assets")).unwrap();
+        let assets = Assets::new(&context.gl, res);
 
         let physical_size = size.to_physical(window.hidpi_factor());
         context.window.resize(physical_size);
 
-        let color_buffer = ColorBuffer::from_color(nalgebra::Vector3::new(0.0,0.0,0.0));
+        let mut root = ConsoleRoot::new((size.width as f32, size.height as f32));
+        root.add_transformer(Transformer::Letterbox(16.0 / 12.0, *theme::BACKGROUND));
+        let bars = root.bar_color().unwrap_or(*theme::BACKGROUND);
+        let color_buffer = ColorBuffer::from_color(nalgebra::Vector3::new(bars.x, bars.y, bars.z));
         color_buffer.set_used(&context.gl);
 
         let camera = Camera::new(size, Vec3::new(0.0, 0.0, 3.0), Vec3::new(0.0, 0.0, 0.0), &window);
@@ -278,43 +358,49 @@ impl Game for GameImpl  {
         let font_size = (1.0 / 120.0, 1.0 / 40.0);
         let console = ConsoleBuilder::with_dimensions(map_size)
             .scale((1.0, 0.75))
-            .top_align()
+            .anchor(Anchor::Top)
             .background(*theme::BACKGROUND)
             .layer(1)
-            .build(&res, &context.gl)
+            .build(&assets)
             .unwrap();
+        let console = root.add(console);
 
         let console_term = ConsoleBuilder::with_dimensions((15, 8))
             .scale((0.2, 0.15))
-            .font_from(&console)
-            .top_align()
-            .right_align()
+            .font_from(&console.borrow())
+            .anchor(Anchor::TopRight)
             .background(*theme::BACKGROUND)
             .layer(2)
-            .build(&res, &context.gl)
+            .build(&assets)
             .unwrap();
+        let console_term = root.add(console_term);
 
         let console_message_log = ConsoleBuilder::with_dimensions((120, 12))
                 .scale((1.0, 0.25))
-                .font_from(&console)
+                .anchor(Anchor::Bottom)
+                .font_from(&console.borrow())
                 .background(*theme::BACKGROUND)
                 .layer(1)
-                .build(&res, &context.gl)
+                .build(&assets)
                 .unwrap();
+        let console_message_log = root.add(console_message_log);
 
         let game_over = ConsoleBuilder::with_dimensions((10, 1))
             .scale((0.5, 0.1))
-            .font_from(&console)
+            .font_from(&console.borrow())
             .background(*theme::BACKGROUND)
             .layer(10)
-            .centered(true)
-            .relative_to(&console)
-            .build(&res, &context.gl)
+            .anchor(Anchor::Center)
+            .relative_to(&console.borrow())
+            .build(&assets)
             .unwrap();
+        let game_over = root.add_child(&console, game_over);
+        game_over.borrow_mut().put_text("Game over", 0, 0, *theme::RED_ALERT_TEXT, None, 2);
+        game_over.borrow_mut().set_visible(false);
 
 
-        let mut message_log = ScrollingMessageConsole::new(console_message_log, 12);
-        message_log.add_colored_message("Oh man this is spooky.", *theme::RED_ALERT_TEXT);
+        let mut message_log = ScrollingMessageConsole::new(console_message_log);
+        message_log.add_markup("{red_alert}Oh man this is {bold}spooky{/}.{/}");
         let input_map: HashMap<VirtualKeyCode, bool> = [].iter().cloned().collect();
         let mut objects = vec![];
         objects.push(Object::new((0, 0), '@', *theme::PLAYER, "player", true));
@@ -330,10 +416,11 @@ impl Game for GameImpl  {
             power: 5,
             on_death: DeathCallback::Player,
         });
-        let mut game = GameImpl {
+        GameImpl {
             has_moved: true,
             color_buffer,
             camera,
+            root,
             console,
             objects,
             map,
@@ -344,10 +431,10 @@ impl Game for GameImpl  {
             keyboard:input_map,
             input_limiter: Instant::now(),
             active_menu: None,
+            pressed_option: None,
             font_size,
-        };
-        game.set_window_ratios(size);
-        game
+            assets,
+        }
     }
 
 
@@ -361,17 +448,16 @@ impl Game for GameImpl  {
             self.has_moved = false;
         }
 
-        self.console.render(&gl);
-        self.console_term.clear();
-        if let Some(fighter) = self.objects[0].fighter {
-            self.console_term.put_text(&format!("HP: {}/{}", fighter.hp, fighter.max_hp), 0, 7, *theme::PLAYER, Some(*colors::CLEAR), 2);
+        {
+            let mut console_term = self.console_term.borrow_mut();
+            console_term.clear();
+            if let Some(fighter) = self.objects[0].fighter {
+                console_term.put_text(&format!("HP: {}/{}", fighter.hp, fighter.max_hp), 0, 7, *theme::PLAYER, Some(*colors::CLEAR), 2);
+            }
         }
+        self.game_over.borrow_mut().set_visible(!self.objects[0].alive);
 
-        self.console_term.render(&gl);
-        self.message_log.render(&gl);
-        if let Some(menu) = &mut self.active_menu {
-            menu.render(&gl);
-        }
+        self.root.render();
     }
 
     fn update(&mut self, pending_input: Option<InputEvent>, _dt: f32, context: &GameContext) {
@@ -391,6 +477,6 @@ impl Game for GameImpl  {
     }
 
     fn resize(&mut self, size: LogicalSize) {
-        self.set_window_ratios(size);
+        self.root.resize((size.width as f32, size.height as f32));
     }
 }
\ No newline at end of file
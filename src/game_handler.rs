@@ -9,11 +9,11 @@ use glutin::{
     CreationError,
     dpi::LogicalSize,
     window::{Window, WindowBuilder},
-    event::{Event, WindowEvent, DeviceEvent, KeyboardInput, VirtualKeyCode, ElementState, MouseScrollDelta},
+    event::{Event, WindowEvent, DeviceEvent, KeyboardInput, VirtualKeyCode, ElementState, MouseScrollDelta, MouseButton},
     event_loop::{EventLoop, ControlFlow},
     GlRequest,
     Api};
-use crate::game_handler::InputEvent::{KeyPressed, KeyReleased, MouseMoved};
+use crate::game_handler::InputEvent::{KeyPressed, KeyReleased, MouseMoved, CursorMoved, MousePressed, MouseReleased};
 use failure::_core::time::Duration;
 use std::ops::Add;
 use std::thread;
@@ -46,6 +46,11 @@ pub enum InputEvent {
     KeyPressed(InputEventData<VirtualKeyCode>),
     KeyReleased(InputEventData<VirtualKeyCode>),
     MouseMoved(f32, f32),
+    // Cursor position in pixels from the window's top left corner
+    CursorMoved(InputEventData<(f32, f32)>),
+    // The button and where the cursor was when it changed
+    MousePressed(InputEventData<(MouseButton, (f32, f32))>),
+    MouseReleased(InputEventData<(MouseButton, (f32, f32))>),
 }
 #[derive(Debug, Clone, Copy)]
 pub struct InputEventData<T> {
@@ -115,6 +120,7 @@ impl GameHandler {
         let event_loop = self.event_loop.take().unwrap();
         let mut game = G::new(&context, self.size);
         let mut fps_updater = Instant::now();
+        let mut cursor_position = (0.0, 0.0);
         event_loop.run(move |event, _, control_flow| {
             let now = Instant::now();
             let dt = context.dt(last_frame);
@@ -170,6 +176,38 @@ impl GameHandler {
                         MouseMoved(x as f32, y as f32)
                     );
                 },
+                Event::WindowEvent {
+                    event: WindowEvent::CursorMoved {
+                        position,
+                        ..
+                    },
+                    ..
+                } => {
+                    cursor_position = (position.x as f32, position.y as f32);
+                    pending_input = Some(CursorMoved(
+                        InputEventData {
+                            data: cursor_position,
+                            dt,
+                        })
+                    );
+                },
+                Event::WindowEvent {
+                    event: WindowEvent::MouseInput {
+                        state,
+                        button,
+                        ..
+                    },
+                    ..
+                } => {
+                    let data = InputEventData {
+                        data: (button, cursor_position),
+                        dt,
+                    };
+                    pending_input = Some(match state {
+                        ElementState::Pressed => MousePressed(data),
+                        ElementState::Released => MouseReleased(data),
+                    });
+                },
                 Event::WindowEvent {
                     event: WindowEvent::MouseWheel {
                         delta,
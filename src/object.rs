@@ -1,4 +1,4 @@
-use console_backend::Color;
+use console_backend::{Color, Span, Style};
 use crate::map::{Map, move_by};
 use std::cmp;
 use crate::widgets::scrolling_message_console::ScrollingMessageConsole;
@@ -72,18 +72,28 @@ impl Object {
     pub fn attack(&self, target: &mut Object, messages: &mut ScrollingMessageConsole) {
         let damage = self.fighter.map_or(0, |f| f.power) - target.fighter.map_or(0, |f| f.defense);
         if damage > 0 {
-            messages.add_message(format!(
-                "{} attacks {} for {} hit points.",
-                self.name, target.name, damage).as_str());
+            messages.add_spans(vec![
+                self.name_span(),
+                Span::new(" attacks "),
+                target.name_span(),
+                Span::new(&format!(" for {} hit points.", damage)),
+            ]);
             target.take_damage(damage, messages);
         } else {
-            messages.add_message(format!(
-                "{} attacks {} but it has no effect!",
-                self.name, target.name
-            ).as_str());
+            messages.add_spans(vec![
+                self.name_span(),
+                Span::new(" attacks "),
+                target.name_span(),
+                Span::new(" but it has no effect!"),
+            ]);
         }
     }
 
+    // The name in bold and in the object's own color, for the message log
+    fn name_span(&self) -> Span {
+        Span::colored(&self.name, self.color).style(Style::BOLD)
+    }
+
     pub fn heal(&mut self, amount: i32) {
         if let Some(ref mut fighter) = self.fighter {
             fighter.hp = clamp(fighter.hp + amount, 0, fighter.max_hp);
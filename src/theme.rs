@@ -2,6 +2,7 @@
 pub mod theme {
     use console_backend::{
         Color,
+        Palette,
         colors,
     };
     // TODO: MOVE THIS INTO A CONFIG
@@ -20,5 +21,19 @@ pub mod theme {
         pub static ref ORC: Color = *colors::LIGHT_GREEN;
         pub static ref HEALING_ITEM: Color = *colors::LIGHT_SLATE_BLUE;
         pub static ref SCROLL_ITEM: Color = *colors::LIGHT_YELLOW;
+        // Names usable in message markup, on top of the backend's colors
+        pub static ref PALETTE: Palette = {
+            let mut palette = Palette::default();
+            palette
+                .insert("red_alert", *RED_ALERT_TEXT)
+                .insert("green_alert", *GREEN_ALERT_TEXT)
+                .insert("player", *PLAYER)
+                .insert("blood", *BLOOD)
+                .insert("troll", *TROLL)
+                .insert("orc", *ORC)
+                .insert("healing_item", *HEALING_ITEM)
+                .insert("scroll_item", *SCROLL_ITEM);
+            palette
+        };
     }
 }
\ No newline at end of file
@@ -1,58 +1,89 @@
-use console_backend::{Console, ConsoleBuilder};
-use console_backend::resources::Resources;
-use std::path::Path;
+use console_backend::{ConsoleBuilder, ConsoleHandle, ConsoleRoot, Assets, Style, Align, Anchor, Rect, wrap_text};
 use crate::theme::theme;
 use glutin::{
     event::VirtualKeyCode,
 };
 
 pub struct Menu {
-    pub console: Console,
+    pub console: ConsoleHandle,
     options: Vec<String>,
     header: String,
     height: u32,
+    header_rows: u32,
+    // Option under the mouse, drawn reversed
+    hovered: Option<usize>,
 }
 
 impl Menu {
-    pub fn new(gl: &gl::Gl,
+    pub fn new(assets: &Assets,
                header: String,
                options: Vec<String>,
                width: u32,
                font_size: (f32, f32),
-               relative_console: &Console) -> Self {
-        let res = Resources::from_relative_exe_path(Path::new("assets")).unwrap();
-        let height = options.len() as u32 + 2;
+               root: &mut ConsoleRoot,
+               parent: &ConsoleHandle) -> Self {
+        let height = Menu::height_for(&header, &options, width);
         let console = ConsoleBuilder::with_dimensions_and_font_size((width, height), font_size)
-            .relative_to(relative_console)
-            .font_from(relative_console)
-            .centered(true)
+            .relative_to(&parent.borrow())
+            .font_from(&parent.borrow())
+            .anchor(Anchor::Center)
             .layer(99)
-            .build(&res, &gl)
+            .build(assets)
             .unwrap();
+        Menu::with_console(root.add_child(parent, console), header, options)
+    }
+
+    pub fn with_console(console: ConsoleHandle, header: String, options: Vec<String>) -> Self {
+        let height = Menu::height_for(&header, &options, console.borrow().dimensions().0);
         let mut menu = Menu {
             console,
             options,
             header,
             height,
+            header_rows: 0,
+            hovered: None,
         };
         menu.init_buffer();
         menu
     }
 
+    // The header wrapped to width, the options and a blank row below them
+    fn height_for(header: &str, options: &[String], width: u32) -> u32 {
+        wrap_text(header, width).len() as u32 + options.len() as u32 + 1
+    }
+
     pub fn init_buffer(&mut self) {
-        self.console.clear();
-        self.console.fill_background(*theme::BACKGROUND);
-        self.console.put_text(&self.header, 0, (self.height - 1) as i32, *theme::REGULAR_ALERT_TEXT, None, 2);
+        let mut console = self.console.borrow_mut();
+        console.clear();
+        console.fill_background(*theme::BACKGROUND);
+        let width = console.dimensions().0;
+        self.header_rows = console.print_rect(Rect::new(0, 0, width, self.height), &self.header, Align::Left, true, *theme::REGULAR_ALERT_TEXT, None, 2);
         for (index, option) in self.options.iter().enumerate() {
             let menu_letter = (b'a' + index as u8) as char;
             let option = format!("({}) {}", menu_letter, option);
-            let y = self.height as i32 - 2 - index as i32;
-            self.console.put_text(&option, 0, y, *theme::REGULAR_ALERT_TEXT, None, 2);
+            let y = self.height as i32 - 1 - self.header_rows as i32 - index as i32;
+            let style = if self.hovered == Some(index) { Style::REVERSE } else { Style::NONE };
+            console.put_text_styled(&option, 0, y, *theme::REGULAR_ALERT_TEXT, None, style, 2);
+            console.put_char_styled(menu_letter, 1, y, *theme::REGULAR_ALERT_TEXT, None, style | Style::UNDERLINE, 2);
         }
     }
 
-    pub fn render(&mut self, gl: &gl::Gl) {
-        self.console.render(&gl);
+    pub fn hover(&mut self, option: Option<usize>) {
+        if option != self.hovered {
+            self.hovered = option;
+            self.init_buffer();
+        }
+    }
+
+    // The option drawn on the row of cell
+    pub fn option_at(&self, cell: (u32, u32)) -> Option<usize> {
+        let first_row = self.height.checked_sub(1 + self.header_rows)?;
+        let index = first_row.checked_sub(cell.1)? as usize;
+        if index < self.options.len() {
+            Some(index)
+        } else {
+            None
+        }
     }
 
     pub fn process_input(&mut self, key: VirtualKeyCode) -> Option<usize> {
@@ -71,4 +102,31 @@ impl Menu {
         }
     }
 
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn menu() -> Menu {
+        let console = ConsoleBuilder::with_dimensions((12, 4)).build_headless();
+        Menu::with_console(Rc::new(RefCell::new(console)), "Header".into(), vec!["one".into(), "two".into()])
+    }
+
+    #[test]
+    fn options_are_under_the_header() {
+        let menu = menu();
+        assert_eq!(menu.console.borrow().row_text(2).trim_end(), "(a) one");
+        assert_eq!(menu.option_at((0, 2)), Some(0));
+        assert_eq!(menu.option_at((11, 1)), Some(1));
+    }
+
+    #[test]
+    fn the_header_and_blank_row_are_no_option() {
+        let menu = menu();
+        assert_eq!(menu.option_at((0, 3)), None);
+        assert_eq!(menu.option_at((0, 0)), None);
+        assert_eq!(menu.option_at((0, 9)), None);
+    }
+}
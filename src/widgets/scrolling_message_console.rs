@@ -1,20 +1,24 @@
+use std::cell::RefCell;
 use std::collections::VecDeque;
-use console_backend::Console;
-use console_backend::{Color, colors};
+use std::rc::Rc;
+use console_backend::{Console, ConsoleHandle};
+use console_backend::{Color, colors, Span, Align, Rect, FrameStyle, parse_markup, wrap_spans};
 use crate::theme::theme;
 
 pub struct ScrollingMessageConsole {
-    messages: VecDeque<(String, Color)>,
-    pub console: Console,
-    height: u32,
+    // Shared with the console's resize hook, which draws them again at the new size
+    messages: Rc<RefCell<VecDeque<Vec<Span>>>>,
+    pub console: ConsoleHandle,
 }
 
 impl ScrollingMessageConsole {
-    pub fn new(console: Console, height: u32) -> Self {
+    pub fn new(console: ConsoleHandle) -> Self {
+        let messages = Rc::new(RefCell::new(VecDeque::new()));
+        let shared = Rc::clone(&messages);
+        console.borrow_mut().on_resize(move |console, _| draw_messages(console, &shared.borrow()));
         ScrollingMessageConsole {
             console,
-            messages: VecDeque::new(),
-            height,
+            messages,
         }
     }
 
@@ -23,40 +27,56 @@ impl ScrollingMessageConsole {
     }
 
     pub fn add_colored_message(&mut self, message: &str, color: Color) {
-        self.messages.push_back((message.to_string(), color));
-        let extra = (self.messages.len() as i32) - (self.height as i32) + 2;
+        self.add_spans(vec![Span::colored(message, color)]);
+    }
+
+    // Markup as understood by parse_markup, with the theme's color names
+    pub fn add_markup(&mut self, markup: &str) {
+        self.add_spans(parse_markup(markup, &theme::PALETTE));
+    }
 
-        if extra > 0 {
-            for _ in 0..extra {
-                self.messages.pop_front();
+    pub fn add_spans(&mut self, spans: Vec<Span>) {
+        {
+            let height = self.console.borrow().dimensions().1;
+            let mut messages = self.messages.borrow_mut();
+            messages.push_back(spans);
+            let extra = (messages.len() as i32) - (height as i32) + 2;
+
+            if extra > 0 {
+                for _ in 0..extra {
+                    messages.pop_front();
+                }
             }
         }
         self.refresh_buffer();
     }
 
     pub fn refresh_buffer(&mut self) {
-        let clear: Color = Color::from_int(0, 0, 0, 0.0);
-        self.console.clear();
-        let x = "║═╔╗╚╝";
-        let (width, height) = self.console.dimensions();
-        let top = format!("╔{}╗", std::iter::repeat("═").take(width as usize - 2).collect::<String>());
-        let bottom = format!("╚{}╝", std::iter::repeat("═").take(width as usize - 2).collect::<String>());
-        self.console.put_text(&top, 0, self.height as i32 - 1, *colors::WHITE, Some(*colors::CLEAR), 3);
-        let mut current_height: i32 = (self.height - 2) as i32;
-        for (message, color) in self.messages.iter() {
-            self.console.put_char('║', 0, current_height as i32, *colors::WHITE, Some(*colors::CLEAR), 3);
-            self.console.put_text(message, 1 , current_height as i32, *color, Some(*colors::CLEAR), 3);
-            self.console.put_char('║', width as i32 - 1, current_height as i32, *colors::WHITE, Some(*colors::CLEAR), 3);
-            current_height -= 1;
-        }
-        for n in 1..=current_height {
-            self.console.put_char('║', 0, n as i32, *colors::WHITE, Some(*colors::CLEAR), 3);
-            self.console.put_char('║', width as i32 - 1, n as i32, *colors::WHITE, Some(*colors::CLEAR), 3);
-        }
-        self.console.put_text(&bottom, 0, 0, *colors::WHITE, Some(*colors::CLEAR), 3);
+        draw_messages(&mut self.console.borrow_mut(), &self.messages.borrow());
     }
+}
 
-    pub fn render(&mut self, gl: &gl::Gl) {
-        self.console.render(&gl);
+fn draw_messages(console: &mut Console, messages: &VecDeque<Vec<Span>>) {
+    console.clear();
+    let (width, height) = console.dimensions();
+    if width < 3 || height < 3 {
+        return;
+    }
+    console.draw_frame(Rect::new(0, 0, width, height), FrameStyle::Double, None, *colors::WHITE, Some(*colors::CLEAR), 3);
+    // Newest messages that fit once wrapped, drawn oldest first from the top
+    let rows = height - 2;
+    let mut shown = 0;
+    let mut used = 0;
+    for spans in messages.iter().rev() {
+        used += wrap_spans(spans, width - 2).len() as u32;
+        if used > rows && shown > 0 {
+            break;
+        }
+        shown += 1;
     }
-}
\ No newline at end of file
+    let mut free_rows = rows;
+    for spans in messages.iter().skip(messages.len() - shown) {
+        let area = Rect::new(1, 1, width - 2, free_rows);
+        free_rows -= console.print_rect_spans(area, spans, Align::Left, true, *theme::REGULAR_ALERT_TEXT, Some(*colors::CLEAR), 3);
+    }
+}